@@ -0,0 +1,65 @@
+extern crate animator_lib;
+#[macro_use]
+extern crate criterion;
+extern crate nalgebra_glm as glm;
+
+use animator_lib::skeleton::Skeleton;
+use animator_lib::pose::Pose;
+use criterion::{Criterion, Fun};
+use glm::Vec3;
+
+// A rig shaped like a body, a spine with limbs that are chains of 4 joints
+fn build_rig(joints: usize) -> Skeleton {
+    let mut tree = vec![None];
+    let mut spine = 0;
+
+    while tree.len() < joints {
+        tree.push(Some(spine));
+        spine = tree.len() - 1;
+
+        let mut parent = spine;
+        for _ in 0..4 {
+            if tree.len() >= joints {
+                break;
+            }
+            tree.push(Some(parent));
+            parent = tree.len() - 1;
+        }
+    }
+
+    let poses = (0..tree.len()).map(|i| {
+        let rotation = glm::quat_angle_axis(0.1 * i as f32, &Vec3::new(0.0, 1.0, 0.0));
+        Pose::without_scale(Vec3::new(0.0, 1.0, 0.0), rotation)
+    }).collect();
+
+    let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+    skeleton.build_inv_bind_poses();
+    skeleton
+}
+
+fn world_poses(c: &mut Criterion) {
+    for joints in &[50, 200, 1000] {
+        let recursive = Fun::new("recursive", |b, rig: &Skeleton| {
+            let mut rig = rig.clone();
+            b.iter(|| rig.build_world_poses_recursive())
+        });
+        let linear = Fun::new("linear", |b, rig: &Skeleton| {
+            let mut rig = rig.clone();
+            b.iter(|| rig.build_world_poses_linear())
+        });
+        let flat = Fun::new("flat", |b, rig: &Skeleton| {
+            let mut world = vec![];
+            b.iter(|| rig.world_poses_into(&mut world))
+        });
+        let palette = Fun::new("palette", |b, rig: &Skeleton| {
+            let mut world = vec![];
+            let mut palette = vec![];
+            b.iter(|| rig.matrix_palette_into(&mut world, &mut palette).unwrap())
+        });
+
+        c.bench_functions(&format!("world_poses_{}", joints), vec![recursive, linear, flat, palette], build_rig(*joints));
+    }
+}
+
+criterion_group!(benches, world_poses);
+criterion_main!(benches);
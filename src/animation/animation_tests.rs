@@ -88,4 +88,92 @@ fn animator_outside_test() {
 
     assert!(animator.current_frame() == (1, 1.0));
     assert!(animator.next_frame() == (2, 2.0));
-}
\ No newline at end of file
+}
+#[test]
+fn animator_morph_weights_test() {
+    let mut animation = super::Animation::new(1, None);
+    animation.set_morph_weights(2, vec![]);
+    animation.add_frame_with_weights(&[Pose::pose_identity()], &[0.0, 1.0], 0.0);
+    animation.add_frame_with_weights(&[Pose::pose_identity()], &[1.0, 0.0], 1.0);
+
+    let mut animator = Animator::new();
+    animator.time = 0.25;
+    animator.update_frames(animation.sample_times());
+
+    let mut weights = [0.0; 2];
+    animator.write_morph_weights(&animation, &mut weights).unwrap();
+
+    assert!((weights[0] - 0.25).abs() < 1e-6);
+    assert!((weights[1] - 0.75).abs() < 1e-6);
+
+    // Frames without weights are padded with zeros
+    animation.add_frame(&[Pose::pose_identity()], 2.0);
+    assert!(animation.get_frame_weights(2) == Some(&[0.0, 0.0][..]));
+    assert!(animation.get_frame_weights(3).is_none());
+}
+
+#[test]
+fn animator_property_test() {
+    use super::property::PropertyTrack;
+    use std::collections::HashMap;
+
+    let poses = [Pose::pose_identity(), Pose::pose_identity()];
+    let mut animation = super::Animation::from_poses_and_times(1, &poses, &[0.0, 2.0], None);
+    animation.add_property_track(PropertyTrack::from_values("fov", 1, vec![60.0, 90.0]));
+    animation.add_property_track(PropertyTrack::from_values("color", 3, vec![0.0, 0.0, 0.0, 1.0, 0.5, 0.0]));
+
+    let mut animator = Animator::new();
+    animator.time = 1.0;
+    animator.update_frames(animation.sample_times());
+
+    let mut properties: HashMap<String, Vec<f32>> = HashMap::new();
+    animator.write_properties(&animation, &mut properties).unwrap();
+
+    assert!(properties["fov"] == vec![75.0]);
+    assert!(properties["color"] == vec![0.5, 0.25, 0.0]);
+
+    // Frames added afterwards hold the last value
+    animation.add_frame(&[Pose::pose_identity()], 4.0);
+    assert!(animation.property_track("fov").unwrap().get_frame(2) == Some(&[90.0][..]));
+    assert!(animation.property_track("color").unwrap().frames() == 3);
+}
+
+#[test]
+fn property_track_deserialize_test() {
+    use super::property::PropertyTrack;
+    use serde_json;
+
+    assert!(serde_json::from_str::<PropertyTrack>(r#"{"name": "fov", "components": 1, "values": [60.0]}"#).is_ok());
+    assert!(serde_json::from_str::<PropertyTrack>(r#"{"name": "fov", "components": 0, "values": []}"#).is_err());
+    assert!(serde_json::from_str::<PropertyTrack>(r#"{"name": "color", "components": 3, "values": [1.0]}"#).is_err());
+}
+
+#[test]
+fn animator_shorter_animation_test() {
+    let mut animator = Animator::new();
+    animator.time = 5.5;
+    animator.update_frames(&[0.0, 2.0, 4.0, 6.0, 8.0]);
+    assert!(animator.current_frame() == (2, 4.0));
+
+    // The animation was reloaded with fewer keyframes
+    animator.time = 1.5;
+    animator.update_frames(&[0.0, 1.0, 2.0]);
+
+    assert!(animator.current_frame() == (1, 1.0));
+    assert!(animator.next_frame() == (2, 2.0));
+}
+
+#[test]
+fn filter_targets_test() {
+    let poses: Vec<Pose> = (0..6).map(|i| Pose::only_trans(i as f32, 0.0, 0.0)).collect();
+    let animation = super::Animation::from_poses_and_times(3, &poses, &[0.0, 1.0], Some(vec![4, 1, 2]));
+
+    // Joint 1 was left out, joint 2 became 0 and joint 4 became 1
+    let filtered = animation.filter_targets(&[None, None, Some(0), None, Some(1)]);
+
+    assert!(filtered.keyframes == 2);
+    assert!(filtered.bones == 2);
+    assert!(filtered.targets == Some(vec![1, 0]));
+    let translations: Vec<f32> = filtered.poses.iter().map(|x| x.translation.x).collect();
+    assert!(translations == vec![0.0, 2.0, 3.0, 5.0]);
+}
@@ -1,6 +1,6 @@
 use pose::*;
 use math;
-use animation::traits::{Animation, AnimationTarget, Targets};
+use animation::traits::{Animation, AnimationTarget, PropertyTarget, Targets};
 use std::{cmp::PartialOrd, fmt, error};
 use glm::*;
 
@@ -57,9 +57,21 @@ impl Animator {
         self.next_frame
     }
     
+    // Forgets the cached frames if they don't belong to sample_times, which happens when the
+    // animation is swapped for one with different or fewer keyframes
+    pub fn clamp_frames(&mut self, sample_times: &[f32]) {
+        let valid = |frame: (usize, f32)| sample_times.get(frame.0) == Some(&frame.1);
+
+        if !valid(self.current_frame) || !valid(self.next_frame) {
+            self.current_frame = (0, 0.0);
+            self.next_frame = (0, 0.0);
+        }
+    }
+
     pub fn update_frames(&mut self, sample_times: &[f32]) {
         let len = sample_times.len();
         assert!(len > 0);
+        self.clamp_frames(sample_times);
 
         if self.time >= self.current_frame.1 && self.time < self.next_frame.1 {
             return;
@@ -145,6 +157,78 @@ impl Animator {
 
     }   
 
+    // How far the current time is between the current and next frame
+    fn interpolation_factor(&self, frames: usize, ctime: f32, ntime: f32) -> f32 {
+        if self.current_frame.0 == frames - 1 {
+            match self.loop_time {
+                Some(duration) => (self.time - ctime) / duration,
+                None => 0.0,
+            }
+        }
+        else {
+            (self.time - ctime) / (ntime - ctime)
+        }
+    }
+
+    // Writes the interpolated blend shape weights into weights, does nothing if the animation
+    // has no weights
+    pub fn write_morph_weights<A: Animation>(&self, animation: &A, weights: &mut [f32]) -> Result<(), MissingFrameError> {
+        let frames = animation.frames();
+        if frames == 0 {
+            return Ok(());
+        }
+
+        let (_, ctime) = animation.get_frame(self.current_frame.0).ok_or(MissingFrameError::new(self.current_frame.0))?;
+        let (_, ntime) = animation.get_frame(self.next_frame.0).ok_or(MissingFrameError::new(self.next_frame.0))?;
+
+        let (current, next) = match (animation.get_morph_weights(self.current_frame.0), animation.get_morph_weights(self.next_frame.0)) {
+            (Some(current), Some(next)) => (current, next),
+            _ => return Ok(()),
+        };
+
+        let interpolate = math::clampf32(self.interpolation_factor(frames, ctime, ntime), 0.0, 1.0);
+        for ((weight, a), b) in weights.iter_mut().zip(current).zip(next) {
+            *weight = a * (1.0 - interpolate) + b * interpolate;
+        }
+
+        Ok(())
+    }
+
+    pub fn manipulate_properties<A, T, F>(&self, animation: &A, target: &mut T, mut function: F) -> Result<(), MissingFrameError>
+    where
+        A: Animation,
+        T: PropertyTarget,
+        F: FnMut(&str, &[f32], &mut T),
+    {
+        let frames = animation.frames();
+        if frames == 0 || animation.property_tracks().is_empty() {
+            return Ok(());
+        }
+
+        let (_, ctime) = animation.get_frame(self.current_frame.0).ok_or(MissingFrameError::new(self.current_frame.0))?;
+        let (_, ntime) = animation.get_frame(self.next_frame.0).ok_or(MissingFrameError::new(self.next_frame.0))?;
+        let interpolate = math::clampf32(self.interpolation_factor(frames, ctime, ntime), 0.0, 1.0);
+
+        let mut value = vec![];
+        for track in animation.property_tracks() {
+            if !track.interpolate_into(self.current_frame.0, self.next_frame.0, interpolate, &mut value) {
+                return Err(MissingFrameError::new(self.next_frame.0.max(self.current_frame.0)));
+            }
+
+            function(track.name.as_str(), value.as_slice(), target);
+        }
+
+        Ok(())
+    }
+
+    pub fn write_properties<A: Animation, T: PropertyTarget>(&self, animation: &A, target: &mut T) -> Result<(), MissingFrameError> {
+        self.manipulate_properties(animation, target, |name, value, target| target.set_property(name, value))
+    }
+
+    pub fn add_properties<A: Animation, T: PropertyTarget>(&self, animation: &A, target: &mut T) -> Result<(), MissingFrameError> {
+        self.manipulate_properties(animation, target, |name, value, target| target.add_property(name, value))
+    }
+
     pub fn manipulate_pose<A, T, F>(&self, animation: &A, targets: &mut [T], mut function: F) -> Result<(), MissingFrameError> 
     where
         A: Animation,
@@ -156,21 +240,10 @@ impl Animator {
             return Ok(());
         }
 
-        let last = frames - 1;
         let (cposes, ctime) = animation.get_frame(self.current_frame.0).ok_or(MissingFrameError::new(self.current_frame.0))?;
         let (nposes, ntime)= animation.get_frame(self.next_frame.0).ok_or(MissingFrameError::new(self.next_frame.0))?;
 
-        let interpolate;
-
-        if self.current_frame.0 == last {
-            match self.loop_time {
-                Some(duration) => interpolate = (self.time - ctime) / duration,
-                None => interpolate = 0.0,
-            }
-        }
-        else {
-            interpolate = (self.time - ctime) / (ntime - ctime);
-        }
+        let interpolate = self.interpolation_factor(frames, ctime, ntime);
 
         match animation.get_targets() {
             Targets::Specified(array) => {
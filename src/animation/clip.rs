@@ -0,0 +1,151 @@
+use super::Animation;
+use super::library::AniLibrary;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// Key times this close to a clip's start or end are not sampled twice
+const TIME_EPSILON: f32 = 1e-5;
+
+// A named part of a longer timeline
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClipRange {
+    pub name: String,
+    pub start: f32,
+    pub end: f32,
+}
+
+impl ClipRange {
+    // Panics if the range ends before it starts, ranges read from files are checked first
+    pub fn new(name: impl Into<String>, start: f32, end: f32) -> ClipRange {
+        assert!(start <= end);
+
+        ClipRange {
+            name: name.into(),
+            start,
+            end,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ClipRangeError {
+    pub line: usize,
+}
+
+impl Display for ClipRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid clip range on line {}", self.line)
+    }
+}
+
+impl Error for ClipRangeError {}
+
+// Parses a range list with one "name start end" clip per line, times are in seconds.
+// Empty lines and lines starting with # are skipped
+pub fn parse_clip_ranges(text: &str) -> Result<Vec<ClipRange>, ClipRangeError> {
+    let mut ranges = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let error = ClipRangeError { line: i + 1 };
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() != 3 {
+            return Err(error);
+        }
+
+        let start: f32 = parts[1].parse().map_err(|_| error.clone())?;
+        let end: f32 = parts[2].parse().map_err(|_| error.clone())?;
+        if start.is_nan() || end.is_nan() || start > end {
+            return Err(error);
+        }
+
+        ranges.push(ClipRange::new(parts[0], start, end));
+    }
+
+    Ok(ranges)
+}
+
+pub fn load_clip_ranges(path: impl AsRef<Path>) -> Result<Vec<ClipRange>, Box<Error>> {
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+
+    Ok(parse_clip_ranges(&text)?)
+}
+
+impl Animation {
+    // Cuts out the part of the animation between start and end with the times moved to start
+    // at 0. The ends are interpolated if they don't fall on a keyframe. None if the range ends
+    // before it starts, see sample_at_times for the other cases
+    pub fn clip(&self, start: f32, end: f32) -> Option<Animation> {
+        if start.is_nan() || end.is_nan() || start > end {
+            return None;
+        }
+
+        let mut times = vec![start];
+        times.extend(self.times.iter().cloned().filter(|x| *x > start + TIME_EPSILON && *x < end - TIME_EPSILON));
+        if end - start > TIME_EPSILON {
+            times.push(end);
+        }
+
+        self.sample_at_times(&times)
+    }
+
+    // None if any of the clips can't be cut
+    pub fn split_clips(&self, ranges: &[ClipRange]) -> Option<Vec<(String, Animation)>> {
+        ranges.iter().map(|x| self.clip(x.start, x.end).map(|clip| (x.name.clone(), clip))).collect()
+    }
+}
+
+// Splits the animation and adds every clip to the library under its name. Returns false and
+// adds nothing if any of the clips can't be cut
+pub fn add_clips_to_library(library: &mut AniLibrary<Animation>, animation: &Animation, ranges: &[ClipRange]) -> bool {
+    let clips = match animation.split_clips(ranges) {
+        Some(clips) => clips,
+        None => return false,
+    };
+
+    for (name, clip) in clips {
+        library.add_named_animation(name, clip);
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pose::Pose;
+    use animation::traits::AnimationLibrary;
+
+    #[test]
+    fn split_timeline() {
+        let mut animation = Animation::new(1, None);
+        for i in 0..5 {
+            animation.add_frame(&[Pose::only_trans(i as f32, 0.0, 0.0)], i as f32);
+        }
+
+        let ranges = parse_clip_ranges("# name start end\nwalk 0 2\n\nrun 2.5 4\n").unwrap();
+        assert!(ranges == vec![ClipRange::new("walk", 0.0, 2.0), ClipRange::new("run", 2.5, 4.0)]);
+        assert!(parse_clip_ranges("jump 1").is_err());
+        assert!(parse_clip_ranges("jump 2 1").is_err());
+        assert!(parse_clip_ranges("jump nan 1").is_err());
+
+        let mut library = AniLibrary::new();
+        assert!(add_clips_to_library(&mut library, &animation, &ranges));
+
+        let run = library.get_named("run").unwrap();
+        assert!(run.times == vec![0.0, 0.5, 1.5]);
+        let (poses, _) = run.get_frame_and_time(0);
+        assert!((poses[0].translation.x - 2.5).abs() < 1e-5);
+
+        let walk = library.handle("walk").unwrap();
+        assert!(library.get_by_handle(walk).unwrap().keyframes == 3);
+    }
+}
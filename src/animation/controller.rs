@@ -11,21 +11,39 @@ pub enum InstanceType {
 
 pub struct AnimationInstance {
     pub animator: Animator,
-    animation_index: usize,
+    animation: AnimationHandle,
     instance_type: InstanceType,
 }
 
 impl AnimationInstance {
-    pub fn new(animator: Animator, index: usize, instance_type: InstanceType) -> AnimationInstance {
+    pub fn new(animator: Animator, animation: AnimationHandle, instance_type: InstanceType) -> AnimationInstance {
         AnimationInstance {
             animator,
-            animation_index: index,
+            animation,
             instance_type
         }
     }
 
+    pub fn named<A: Animation, L: AnimationLibrary<A>>(animator: Animator, library: &L, name: &str, instance_type: InstanceType) -> Result<AnimationInstance, MissingAnimationError> {
+        let handle = library.find_animation(name).ok_or_else(|| MissingAnimationError::named(name))?;
+        Ok(AnimationInstance::new(animator, handle, instance_type))
+    }
+
+    pub fn animation(&self) -> AnimationHandle {
+        self.animation
+    }
+
+    fn get_animation<'a, A: Animation, L: AnimationLibrary<A>>(&self, library: &'a L) -> Result<&'a A, MissingAnimationError> {
+        library.get_by_handle(self.animation).ok_or_else(|| {
+            match library.animation_name(self.animation) {
+                Some(name) => MissingAnimationError::named(name),
+                None => MissingAnimationError::new(self.animation.index()),
+            }
+        })
+    }
+
     pub fn update_frames<A: Animation, L: AnimationLibrary<A>>(&mut self, library: &L) -> Result<(), MissingAnimationError> {
-        let animation = library.get_animation(self.animation_index).ok_or(MissingAnimationError { animation: self.animation_index })?;
+        let animation = self.get_animation(library)?;
         let sample_times = animation.sample_times();
 
         self.animator.update_frames(sample_times);
@@ -33,7 +51,7 @@ impl AnimationInstance {
     }
 
     pub fn update_pose<A: Animation, L: AnimationLibrary<A>, T: AnimationTarget>(&self, library: &L, targets: &mut [T]) -> Result<(), Box<error::Error>> {
-        let animation = library.get_animation(self.animation_index).ok_or(MissingAnimationError { animation: self.animation_index })?;
+        let animation = self.get_animation(library)?;
         match &self.instance_type {
             InstanceType::RotationWrite => self.animator.write_rotations(animation, targets)?,
             InstanceType::RotationAdd => self.animator.add_rotations(animation, targets)?,
@@ -43,6 +61,16 @@ impl AnimationInstance {
 
         Ok(())
     }
+
+    pub fn update_properties<A: Animation, L: AnimationLibrary<A>, T: PropertyTarget>(&self, library: &L, target: &mut T) -> Result<(), Box<error::Error>> {
+        let animation = self.get_animation(library)?;
+        match &self.instance_type {
+            InstanceType::AllAdd => self.animator.add_properties(animation, target)?,
+            _ => self.animator.write_properties(animation, target)?,
+        }
+
+        Ok(())
+    }
 }
 
 pub struct Controller {
@@ -81,24 +109,38 @@ impl Controller {
 
         Ok(())
     }
+
+    pub fn update_properties<A: Animation, L: AnimationLibrary<A>, T: PropertyTarget>(&self, library: &L, target: &mut T) -> Result<(), Box<error::Error>> {
+        for animation in &self.animations {
+            animation.update_properties(library, target)?;
+        }
+
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct MissingAnimationError {
-    animation: usize,
+#[derive(Debug, Clone)]
+pub enum MissingAnimationError {
+    Index(usize),
+    Named(String),
 }
 
 impl MissingAnimationError {
     pub fn new(animation: usize) -> MissingAnimationError {
-        MissingAnimationError {
-            animation,
-        }
+        MissingAnimationError::Index(animation)
+    }
+
+    pub fn named(name: impl Into<String>) -> MissingAnimationError {
+        MissingAnimationError::Named(name.into())
     }
 }
 
 impl fmt::Display for MissingAnimationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Missing {} animation", self.animation)
+        match self {
+            MissingAnimationError::Index(animation) => write!(f, "Missing {} animation", animation),
+            MissingAnimationError::Named(name) => write!(f, "Missing animation {}", name),
+        }
     }
 }
 
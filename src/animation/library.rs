@@ -1,18 +1,186 @@
-use super::traits::{Animation, AnimationLibrary};
+use super::traits::{Animation, AnimationLibrary, AnimationHandle};
+use animation;
+use bvh;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// File an animation was loaded from and what it looked like at the time, used to notice changes
+struct Source {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Source {
+    fn new(path: PathBuf) -> Result<Source, Box<Error>> {
+        let metadata = fs::metadata(&path)?;
+
+        Ok(Source {
+            path,
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+
+    // Polls the file's metadata, a missing file doesn't count as a change
+    fn changed(&self) -> bool {
+        match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.modified().ok() != self.modified || metadata.len() != self.len,
+            Err(_) => false,
+        }
+    }
+}
 
 pub struct AniLibrary<A: Animation> {
     animations: Vec<A>,
+    names: Vec<Option<String>>,
+    handles: HashMap<String, AnimationHandle>,
+    sources: Vec<Option<Source>>,
 }
 
 impl<A: Animation> AniLibrary<A> {
     pub fn new() -> AniLibrary<A> {
         AniLibrary {
             animations: vec![],
+            names: vec![],
+            handles: HashMap::new(),
+            sources: vec![],
         }
     }
 
-    pub fn add_animation(&mut self, animation: A) {
+    pub fn add_animation(&mut self, animation: A) -> AnimationHandle {
         self.animations.push(animation);
+        self.names.push(None);
+        self.sources.push(None);
+
+        AnimationHandle::new(self.animations.len() - 1)
+    }
+
+    // An existing animation with the same name stays in the library but loses its name, it
+    // can only be found by its handle
+    pub fn add_named_animation(&mut self, name: impl Into<String>, animation: A) -> AnimationHandle {
+        let name = name.into();
+        let handle = self.add_animation(animation);
+        self.names[handle.index()] = Some(name.clone());
+        if let Some(old) = self.handles.insert(name, handle) {
+            self.names[old.index()] = None;
+        }
+
+        handle
+    }
+
+    pub fn handle(&self, name: &str) -> Option<AnimationHandle> {
+        self.handles.get(name).cloned()
+    }
+
+    pub fn get_named(&self, name: &str) -> Option<&A> {
+        self.handle(name).and_then(|x| self.get_by_handle(x))
+    }
+
+    pub fn len(&self) -> usize {
+        self.animations.len()
+    }
+}
+
+impl AniLibrary<animation::Animation> {
+    // Loads every supported clip in the directory, named by its file name without the
+    // extension. Supports .json (serialized animation::Animation) and .bvh, other files are skipped.
+    // Returns the result of every supported file, a file that fails to load doesn't stop the others
+    pub fn load_dir(&mut self, path: impl AsRef<Path>) -> Result<Vec<(PathBuf, Result<AnimationHandle, Box<Error>>)>, Box<Error>> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut loaded = vec![];
+        for path in paths {
+            match self.load_file(path.clone()) {
+                Ok(Some(handle)) => loaded.push((path, Ok(handle))),
+                Ok(None) => {}
+                Err(error) => loaded.push((path, Err(error))),
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    // Loads a single clip named by its file name, None if the format isn't supported. The file
+    // is remembered so that the clip can be hot reloaded
+    pub fn load_file(&mut self, path: impl Into<PathBuf>) -> Result<Option<AnimationHandle>, Box<Error>> {
+        let path = path.into();
+        let name = match path.file_stem().and_then(|x| x.to_str()) {
+            Some(name) => name.to_string(),
+            None => return Ok(None),
+        };
+
+        let source = Source::new(path.clone())?;
+        let animation = match load_clip(&path)? {
+            Some(animation) => animation,
+            None => return Ok(None),
+        };
+
+        let handle = self.add_named_animation(name, animation);
+        self.sources[handle.index()] = Some(source);
+
+        Ok(Some(handle))
+    }
+
+    // Polls the source files of every loaded clip and reloads the ones that changed on disk.
+    // Reloaded clips replace the old ones in place so existing handles stay valid. Returns the
+    // result of every clip that changed, a file that fails to load keeps the old clip and is
+    // retried on the next call
+    pub fn reload_changed(&mut self) -> Vec<(AnimationHandle, Result<bool, Box<Error>>)> {
+        let mut reloaded = vec![];
+
+        for i in 0..self.sources.len() {
+            let changed = match &self.sources[i] {
+                Some(source) => source.changed(),
+                None => false,
+            };
+
+            if changed {
+                let handle = AnimationHandle::new(i);
+                reloaded.push((handle, self.reload(handle)));
+            }
+        }
+
+        reloaded
+    }
+
+    // Reloads a clip from its source file, returns false if it wasn't loaded from a file
+    pub fn reload(&mut self, handle: AnimationHandle) -> Result<bool, Box<Error>> {
+        let path = match self.sources.get(handle.index()) {
+            Some(Some(source)) => source.path.clone(),
+            _ => return Ok(false),
+        };
+
+        let source = Source::new(path.clone())?;
+        match load_clip(&path)? {
+            Some(animation) => {
+                self.animations[handle.index()] = animation;
+                self.sources[handle.index()] = Some(source);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+// Loads a clip based on the file extension, None if the format isn't supported
+pub fn load_clip(path: &Path) -> Result<Option<animation::Animation>, Box<Error>> {
+    let extension = path.extension().and_then(|x| x.to_str()).map(|x| x.to_lowercase());
+
+    match extension.as_ref().map(|x| x.as_str()) {
+        Some("json") => Ok(Some(animation::Animation::load_from(path)?)),
+        Some("bvh") => Ok(Some(bvh::load_bvh(path)?.animation)),
+        _ => Ok(None),
     }
 }
 
@@ -25,4 +193,131 @@ impl<A: Animation> AnimationLibrary<A> for AniLibrary<A> {
             Some(&self.animations[index])
         }
     }
-}
\ No newline at end of file
+
+    fn find_animation(&self, name: &str) -> Option<AnimationHandle> {
+        self.handle(name)
+    }
+
+    fn animation_name(&self, handle: AnimationHandle) -> Option<&str> {
+        self.names.get(handle.index()).and_then(|x| x.as_ref()).map(|x| x.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pose::Pose;
+    use std::env;
+    use std::fs::File;
+    use std::io::Write;
+
+    #[test]
+    fn load_clips_from_dir() {
+        let dir = env::temp_dir().join("animator_lib_library_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut walk = animation::Animation::new(1, None);
+        walk.add_frame(&[Pose::pose_identity()], 0.0);
+        walk.add_frame(&[Pose::only_trans(1.0, 0.0, 0.0)], 1.0);
+        walk.save_to(dir.join("walk.json")).unwrap();
+
+        let bvh = "HIERARCHY\nROOT Hips\n{\nOFFSET 0 0 0\nCHANNELS 3 Xposition Yposition Zposition\n}\nMOTION\nFrames: 1\nFrame Time: 0.1\n0 1 0\n";
+        File::create(dir.join("idle.bvh")).unwrap().write_all(bvh.as_bytes()).unwrap();
+        File::create(dir.join("notes.txt")).unwrap().write_all(b"not a clip").unwrap();
+        File::create(dir.join("broken.json")).unwrap().write_all(b"not a clip").unwrap();
+
+        let mut library = AniLibrary::new();
+        let loaded = library.load_dir(&dir).unwrap();
+
+        // Sorted by path, the broken clip doesn't stop the ones after it
+        assert!(loaded.len() == 3);
+        assert!(loaded[0].0 == dir.join("broken.json") && loaded[0].1.is_err());
+        assert!(loaded.iter().skip(1).all(|x| x.1.is_ok()));
+        assert!(library.len() == 2);
+        assert!(library.handle("broken").is_none());
+        assert!(library.get_named("walk").unwrap().keyframes == 2);
+
+        let idle = library.find_animation("idle").unwrap();
+        assert!(library.animation_name(idle) == Some("idle"));
+        assert!(library.get_by_handle(idle).unwrap().keyframes == 1);
+        assert!(library.handle("notes").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replace_named_animation() {
+        let mut library = AniLibrary::new();
+        let first = library.add_named_animation("run", animation::Animation::new(1, None));
+        let second = library.add_named_animation("run", animation::Animation::new(2, None));
+
+        assert!(library.handle("run") == Some(second));
+        assert!(library.animation_name(second) == Some("run"));
+        assert!(library.animation_name(first).is_none());
+        assert!(library.get_by_handle(first).unwrap().bones == 1);
+    }
+
+    #[test]
+    fn reload_changed_clip() {
+        let dir = env::temp_dir().join("animator_lib_reload_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("wave.json");
+
+        let mut wave = animation::Animation::new(1, None);
+        wave.add_frame(&[Pose::pose_identity()], 0.0);
+        wave.add_frame(&[Pose::pose_identity()], 1.0);
+        wave.add_frame(&[Pose::pose_identity()], 2.0);
+        wave.save_to(&path).unwrap();
+
+        let mut library = AniLibrary::new();
+        let handle = library.load_file(&path).unwrap().unwrap();
+        assert!(library.reload_changed().is_empty());
+
+        let mut shorter = animation::Animation::new(1, None);
+        shorter.add_frame(&[Pose::pose_identity()], 0.0);
+        shorter.save_to(&path).unwrap();
+
+        let reloaded = library.reload_changed();
+        assert!(reloaded.len() == 1 && reloaded[0].0 == handle);
+        assert!(reloaded[0].1.as_ref().unwrap() == &true);
+        assert!(library.get_by_handle(handle).unwrap().keyframes == 1);
+        assert!(library.handle("wave") == Some(handle));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reload_past_corrupt_clip() {
+        let dir = env::temp_dir().join("animator_lib_reload_corrupt_test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut clip = animation::Animation::new(1, None);
+        clip.add_frame(&[Pose::pose_identity()], 0.0);
+        clip.add_frame(&[Pose::pose_identity()], 1.0);
+        clip.save_to(dir.join("a.json")).unwrap();
+        clip.save_to(dir.join("b.json")).unwrap();
+
+        let mut library = AniLibrary::new();
+        let a = library.load_file(&dir.join("a.json")).unwrap().unwrap();
+        let b = library.load_file(&dir.join("b.json")).unwrap().unwrap();
+
+        File::create(dir.join("a.json")).unwrap().write_all(b"not a clip").unwrap();
+        let mut shorter = animation::Animation::new(1, None);
+        shorter.add_frame(&[Pose::pose_identity()], 0.0);
+        shorter.save_to(dir.join("b.json")).unwrap();
+
+        let reloaded = library.reload_changed();
+        assert!(reloaded.len() == 2);
+        assert!(reloaded[0].0 == a && reloaded[0].1.is_err());
+        assert!(reloaded[1].0 == b && reloaded[1].1.is_ok());
+
+        // The broken clip keeps its old animation, the other one is replaced
+        assert!(library.get_by_handle(a).unwrap().keyframes == 2);
+        assert!(library.get_by_handle(b).unwrap().keyframes == 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
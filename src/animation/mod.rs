@@ -2,10 +2,14 @@ pub mod animator;
 pub mod traits;
 pub mod library;
 pub mod controller;
+pub mod property;
+pub mod resample;
+pub mod clip;
 #[cfg(test)]
 mod animation_tests;
 
 use pose::*;
+use self::property::PropertyTrack;
 use std::io::{BufReader, BufWriter};
 use std::fs::File;
 use std::path::Path;
@@ -19,6 +23,13 @@ pub struct Animation {
     pub poses: Vec<Pose>,
     pub times: Vec<f32>,
     pub targets: Option<Vec<usize>>,
+    // Blend shape weights, keyframes * morph_targets values stored frame by frame
+    #[serde(default)]
+    pub morph_targets: usize,
+    #[serde(default)]
+    pub morph_weights: Vec<f32>,
+    #[serde(default)]
+    pub properties: Vec<PropertyTrack>,
 }
 
 impl Animation {
@@ -43,6 +54,9 @@ impl Animation {
             poses: poses.iter().map(|pose| *pose).collect(),
             times,
             targets,
+            morph_targets: 0,
+            morph_weights: vec![],
+            properties: vec![],
         }
     }
 
@@ -53,6 +67,9 @@ impl Animation {
             poses: Vec::with_capacity(bones * keyframes),
             times: Vec::with_capacity(keyframes),
             targets,
+            morph_targets: 0,
+            morph_weights: vec![],
+            properties: vec![],
         }
     }
 
@@ -63,12 +80,21 @@ impl Animation {
             poses: vec![],
             times: vec![],
             targets,
+            morph_targets: 0,
+            morph_weights: vec![],
+            properties: vec![],
         }
     }
 
+    // Frames added after set_morph_weights get zero weights, property tracks hold their last value
     pub fn add_frame(&mut self, poses: &[Pose], time: impl Into<f32>) {
+        self.push_frame(poses, time.into());
+        let weights = self.morph_weights.len() + self.morph_targets;
+        self.morph_weights.resize(weights, 0.0);
+    }
+
+    fn push_frame(&mut self, poses: &[Pose], time: f32) {
         assert!(poses.len() == self.bones);
-        let time = time.into();
         let time = match self.times.last() {
             Some(t) => {
                 assert!(*t < time);
@@ -80,6 +106,43 @@ impl Animation {
         self.keyframes += 1;
         self.poses.extend(poses);
         self.times.push(time);
+        for track in self.properties.iter_mut() {
+            track.pad_frames(self.keyframes);
+        }
+    }
+
+    // Adds blend shape weights to every keyframe, weights are stored frame by frame
+    pub fn set_morph_weights(&mut self, morph_targets: usize, weights: Vec<f32>) {
+        assert!(weights.len() == morph_targets * self.keyframes);
+
+        self.morph_targets = morph_targets;
+        self.morph_weights = weights;
+    }
+
+    pub fn add_frame_with_weights(&mut self, poses: &[Pose], weights: &[f32], time: impl Into<f32>) {
+        assert!(weights.len() == self.morph_targets);
+
+        self.push_frame(poses, time.into());
+        self.morph_weights.extend(weights);
+    }
+
+    pub fn get_frame_weights(&self, frame: usize) -> Option<&[f32]> {
+        if frame >= self.keyframes || self.morph_targets == 0 {
+            return None;
+        }
+
+        let start = frame * self.morph_targets;
+        self.morph_weights.get(start..start + self.morph_targets)
+    }
+
+    // The track must have a value for every keyframe
+    pub fn add_property_track(&mut self, track: PropertyTrack) {
+        assert!(track.frames() == self.keyframes);
+        self.properties.push(track);
+    }
+
+    pub fn property_track(&self, name: &str) -> Option<&PropertyTrack> {
+        self.properties.iter().find(|x| x.name == name)
     }
 
     pub fn get_frame_and_time(&self, frame: usize) -> (&[Pose], f32) {
@@ -91,6 +154,59 @@ impl Animation {
         (&self.poses[start..end], self.times[frame])
     }
 
+    // Returns the frame at or before time and how far time is towards the next frame,
+    // times outside of the animation are clamped
+    pub fn frame_at_time(&self, time: f32) -> (usize, usize, f32) {
+        resample::key_at_time(&self.times, time)
+    }
+
+    // Writes the interpolated poses at time into out, nothing without keyframes
+    pub fn sample_into(&self, time: f32, out: &mut Vec<Pose>) {
+        if self.keyframes == 0 {
+            out.clear();
+            return;
+        }
+
+        let (current, next, f) = self.frame_at_time(time);
+        let (a, _) = self.get_frame_and_time(current);
+        let (b, _) = self.get_frame_and_time(next);
+
+        out.clear();
+        out.extend(a.iter().zip(b).map(|(a, b)| pose_interp(a, b, f)));
+    }
+
+    // Rewrites the bones targeted by the animation with a table of old joint index -> new
+    // joint index, such as the one returned by Skeleton::reorder
+    pub fn remap_targets(&mut self, remap: &[usize]) {
+        let targets = match self.targets.take() {
+            Some(targets) => targets.iter().map(|x| remap[*x]).collect(),
+            None => remap[..self.bones].to_vec(),
+        };
+
+        self.targets = Some(targets);
+    }
+
+    // Keeps only the bones whose target is in remap, a table of old joint index -> new joint
+    // index such as the one of Skeleton::extract_subtree
+    pub fn filter_targets(&self, remap: &[Option<usize>]) -> Animation {
+        let targets: Vec<usize> = match &self.targets {
+            Some(targets) => targets.clone(),
+            None => (0..self.bones).collect(),
+        };
+        let kept: Vec<(usize, usize)> = targets.iter().enumerate()
+            .filter_map(|(bone, target)| remap.get(*target).cloned().and_then(|x| x).map(|x| (bone, x)))
+            .collect();
+
+        let mut animation = self.clone();
+        animation.bones = kept.len();
+        animation.targets = Some(kept.iter().map(|x| x.1).collect());
+        animation.poses = (0..self.keyframes)
+            .flat_map(|frame| kept.iter().map(move |(bone, _)| self.poses[frame * self.bones + bone]))
+            .collect();
+
+        animation
+    }
+
     pub fn next_frame_time(&self, current_frame: usize) -> Option<f32> {
         let next = current_frame + 1;
         if next >= self.keyframes {
@@ -137,4 +253,12 @@ impl traits::Animation for Animation {
             None => traits::Targets::InOrder,
         }
     }
+
+    fn get_morph_weights(&self, frame: usize) -> Option<&[f32]> {
+        self.get_frame_weights(frame)
+    }
+
+    fn property_tracks(&self) -> &[PropertyTrack] {
+        self.properties.as_slice()
+    }
 }
\ No newline at end of file
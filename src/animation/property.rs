@@ -0,0 +1,100 @@
+use serde::{Deserialize, Deserializer, de};
+
+// Named scalar or vector values sampled at the same keyframes as the bone poses,
+// used for things like material parameters, light intensity or ik weights
+#[derive(Clone, Debug, Serialize)]
+pub struct PropertyTrack {
+    pub name: String,
+    // Number of floats per keyframe, 1 for a scalar, 3 for a vec3 ...
+    pub components: usize,
+    // keyframes * components values stored frame by frame
+    pub values: Vec<f32>,
+}
+
+impl PropertyTrack {
+    pub fn new(name: impl Into<String>, components: usize) -> PropertyTrack {
+        assert!(components > 0);
+
+        PropertyTrack {
+            name: name.into(),
+            components,
+            values: vec![],
+        }
+    }
+
+    pub fn from_values(name: impl Into<String>, components: usize, values: Vec<f32>) -> PropertyTrack {
+        assert!(components > 0 && values.len() % components == 0);
+
+        PropertyTrack {
+            name: name.into(),
+            components,
+            values,
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.values.len() / self.components
+    }
+
+    pub fn add_frame(&mut self, value: &[f32]) {
+        assert!(value.len() == self.components);
+        self.values.extend(value);
+    }
+
+    // Repeats the last value until the track has the number of frames, zeros if it is empty
+    pub fn pad_frames(&mut self, frames: usize) {
+        let last = match self.frames() {
+            0 => vec![0.0; self.components],
+            n => self.values[(n - 1) * self.components..n * self.components].to_vec(),
+        };
+
+        for _ in self.frames()..frames {
+            self.values.extend(&last);
+        }
+    }
+
+    pub fn get_frame(&self, frame: usize) -> Option<&[f32]> {
+        let start = frame * self.components;
+        let end = start + self.components;
+        if end > self.values.len() {
+            return None;
+        }
+
+        Some(&self.values[start..end])
+    }
+
+    // Writes the values in the form "a * (1 - f) + b * f" into to
+    pub fn interpolate_into(&self, a: usize, b: usize, f: f32, to: &mut Vec<f32>) -> bool {
+        let (a, b) = match (self.get_frame(a), self.get_frame(b)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return false,
+        };
+
+        to.clear();
+        to.extend(a.iter().zip(b).map(|(a, b)| a * (1.0 - f) + b * f));
+        true
+    }
+}
+
+#[derive(Deserialize)]
+struct PropertyTrackData {
+    name: String,
+    components: usize,
+    values: Vec<f32>,
+}
+
+// Rejects tracks that new and from_values would panic on
+impl<'de> Deserialize<'de> for PropertyTrack {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<PropertyTrack, D::Error> {
+        let data = PropertyTrackData::deserialize(deserializer)?;
+        if data.components == 0 || data.values.len() % data.components != 0 {
+            return Err(de::Error::custom(format!("property track {} has {} values for {} components", data.name, data.values.len(), data.components)));
+        }
+
+        Ok(PropertyTrack {
+            name: data.name,
+            components: data.components,
+            values: data.values,
+        })
+    }
+}
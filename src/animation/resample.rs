@@ -0,0 +1,214 @@
+use pose::*;
+use super::Animation;
+use super::property::PropertyTrack;
+
+// Key times closer than this are treated as the same time when building a union timeline
+const TIME_EPSILON: f32 = 1e-5;
+
+// The timeline that curves with different key times are resampled onto
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Timeline {
+    // Every key time of every curve
+    Union,
+    // Evenly spaced samples at this many frames per second, the last key time is always included
+    Rate(f32),
+}
+
+// Returns the key at or before time and how far time is towards the next key,
+// times outside of the keys are clamped. Without keys this is key 0, which doesn't exist
+pub fn key_at_time(times: &[f32], time: f32) -> (usize, usize, f32) {
+    if times.is_empty() {
+        return (0, 0, 0.0);
+    }
+
+    let last = times.len() - 1;
+    if time <= times[0] {
+        return (0, 0, 0.0);
+    }
+    if time >= times[last] {
+        return (last, last, 0.0);
+    }
+
+    match times.binary_search_by(|x| x.partial_cmp(&time).unwrap()) {
+        Ok(index) => (index, index, 0.0),
+        Err(index) => {
+            let (start, end) = (times[index - 1], times[index]);
+            (index - 1, index, (time - start) / (end - start))
+        }
+    }
+}
+
+// Interpolated pose of a curve with one pose per key time
+pub fn sample_curve(times: &[f32], poses: &[Pose], time: f32) -> Pose {
+    assert!(times.len() == poses.len());
+
+    let (current, next, f) = key_at_time(times, time);
+    pose_interp(&poses[current], &poses[next], f)
+}
+
+// Builds the common sample times of a set of curves, these are not moved to start at 0
+pub fn build_timeline<'a>(curves: impl IntoIterator<Item = &'a [f32]>, timeline: Timeline) -> Vec<f32> {
+    let mut times: Vec<f32> = curves.into_iter().flat_map(|x| x.iter().cloned()).collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    times.dedup_by(|a, b| (*a - *b).abs() <= TIME_EPSILON);
+
+    match timeline {
+        Timeline::Union => times,
+        Timeline::Rate(fps) => match (times.first(), times.last()) {
+            (Some(start), Some(end)) => fixed_rate_times(*start, *end, fps),
+            _ => vec![],
+        },
+    }
+}
+
+pub fn fixed_rate_times(start: f32, end: f32, fps: f32) -> Vec<f32> {
+    assert!(fps > 0.0);
+
+    let step = 1.0 / fps;
+    let count = ((end - start) / step).floor() as usize;
+    let mut times: Vec<f32> = (0..=count).map(|i| start + i as f32 * step).collect();
+
+    if end - times[times.len() - 1] > TIME_EPSILON {
+        times.push(end);
+    }
+
+    times
+}
+
+impl Animation {
+    // Returns a copy of the animation sampled at fps, morph weights and property tracks are
+    // interpolated the same way as the poses. None if fps isn't positive, see sample_at_times
+    pub fn resample(&self, fps: f32) -> Option<Animation> {
+        if fps.is_nan() || fps <= 0.0 {
+            return None;
+        }
+        if self.keyframes == 0 {
+            return Some(self.clone());
+        }
+
+        let times = fixed_rate_times(*self.times.first()?, *self.times.get(self.keyframes - 1)?, fps);
+        self.sample_at_times(&times)
+    }
+
+    // Builds an animation with a keyframe at each of the increasing times, the new times are
+    // moved to start at 0. An animation without keyframes is returned unchanged. None if the
+    // times don't increase or the poses, weights or property tracks don't match the keyframes,
+    // which can happen with deserialized animations
+    pub fn sample_at_times(&self, times: &[f32]) -> Option<Animation> {
+        if !times.windows(2).all(|x| x[0] < x[1]) || !self.has_consistent_frames() {
+            return None;
+        }
+        if self.keyframes == 0 {
+            return Some(self.clone());
+        }
+
+        let start = times.first().cloned().unwrap_or(0.0);
+        let mut animation = Animation::with_capacity(self.bones, times.len(), self.targets.clone());
+        let mut poses = Vec::with_capacity(self.bones);
+        let mut weights = Vec::with_capacity(times.len() * self.morph_targets);
+        let mut tracks: Vec<PropertyTrack> = self.properties.iter().map(|x| PropertyTrack::new(x.name.clone(), x.components)).collect();
+        let mut values = vec![];
+
+        for time in times {
+            let (current, next, f) = self.frame_at_time(*time);
+            self.sample_into(*time, &mut poses);
+            animation.add_frame(&poses, *time - start);
+
+            if let (Some(a), Some(b)) = (self.get_frame_weights(current), self.get_frame_weights(next)) {
+                weights.extend(a.iter().zip(b).map(|(a, b)| a * (1.0 - f) + b * f));
+            }
+
+            for (track, source) in tracks.iter_mut().zip(&self.properties) {
+                if source.interpolate_into(current, next, f, &mut values) {
+                    track.add_frame(&values);
+                }
+            }
+        }
+
+        if self.morph_targets > 0 {
+            animation.set_morph_weights(self.morph_targets, weights);
+        }
+        for track in tracks {
+            animation.add_property_track(track);
+        }
+
+        Some(animation)
+    }
+
+    // True if every per keyframe array has a value for every keyframe
+    fn has_consistent_frames(&self) -> bool {
+        let frames_of = |per_frame: usize, len: usize| per_frame.checked_mul(self.keyframes) == Some(len);
+
+        self.times.len() == self.keyframes
+            && frames_of(self.bones, self.poses.len())
+            && frames_of(self.morph_targets, self.morph_weights.len())
+            && self.properties.iter().all(|x| x.components > 0 && frames_of(x.components, x.values.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_animation() {
+        let animation = Animation::new(2, None);
+
+        assert!(animation.frame_at_time(1.0) == (0, 0, 0.0));
+        assert!(animation.resample(30.0).unwrap().keyframes == 0);
+        assert!(animation.sample_at_times(&[0.0, 1.0]).unwrap().keyframes == 0);
+
+        let mut poses = vec![Pose::pose_identity()];
+        animation.sample_into(0.5, &mut poses);
+        assert!(poses.is_empty());
+    }
+
+    #[test]
+    fn union_and_rate_timelines() {
+        let a: &[f32] = &[0.0, 0.5, 1.0];
+        let b: &[f32] = &[0.0, 0.25, 1.0];
+
+        assert!(build_timeline(vec![a, b], Timeline::Union) == vec![0.0, 0.25, 0.5, 1.0]);
+        assert!(build_timeline(vec![a, b], Timeline::Rate(2.0)) == vec![0.0, 0.5, 1.0]);
+        assert!(fixed_rate_times(0.0, 1.1, 2.0) == vec![0.0, 0.5, 1.0, 1.1]);
+    }
+
+    #[test]
+    fn resample_animation() {
+        let mut animation = Animation::new(1, None);
+        animation.add_frame(&[Pose::only_trans(0.0, 0.0, 0.0)], 0.0);
+        animation.add_frame(&[Pose::only_trans(2.0, 0.0, 0.0)], 1.0);
+        animation.add_property_track(PropertyTrack::from_values("fade", 1, vec![0.0, 1.0]));
+
+        let resampled = animation.resample(4.0).unwrap();
+        assert!(resampled.keyframes == 5);
+        assert!(resampled.times == vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+
+        let (poses, _) = resampled.get_frame_and_time(1);
+        assert!((poses[0].translation.x - 0.5).abs() < 1e-5);
+        assert!((resampled.properties[0].values[2] - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reject_inconsistent_animation() {
+        let mut animation = Animation::new(1, None);
+        animation.add_frame(&[Pose::pose_identity()], 0.0);
+        animation.add_frame(&[Pose::pose_identity()], 1.0);
+
+        assert!(animation.resample(0.0).is_none());
+        assert!(animation.sample_at_times(&[0.5, 0.5]).is_none());
+
+        let mut weights = animation.clone();
+        weights.morph_targets = 2;
+        weights.morph_weights = vec![0.0; 3];
+        assert!(weights.sample_at_times(&[0.0, 0.5]).is_none());
+
+        let mut property = animation.clone();
+        property.properties.push(PropertyTrack { name: "fade".to_string(), components: 1, values: vec![1.0] });
+        assert!(property.resample(10.0).is_none());
+
+        let mut poses = animation.clone();
+        poses.poses.pop();
+        assert!(poses.sample_at_times(&[0.0]).is_none());
+    }
+}
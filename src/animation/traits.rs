@@ -1,5 +1,7 @@
 use pose::*;
 use glm::{Vec3, Quat};
+use super::property::PropertyTrack;
+use std::collections::HashMap;
 
 pub enum Targets<'a> {
     // The index of each pose corresponds to the index of the bone it targets
@@ -17,6 +19,16 @@ pub trait Animation {
 
     fn get_targets<'a>(&'a self) -> Targets<'a>;
 
+    // Blend shape weights of a frame, None if the animation doesn't drive any
+    fn get_morph_weights(&self, _frame: usize) -> Option<&[f32]> {
+        None
+    }
+
+    // Named float tracks that share the sample times of the poses
+    fn property_tracks(&self) -> &[PropertyTrack] {
+        &[]
+    }
+
     fn frames(&self) -> usize {
         self.sample_times().len()
     }
@@ -79,6 +91,58 @@ impl AnimationTarget for Pose {
     }
 }
 
+// Receives the sampled values of property tracks by name
+pub trait PropertyTarget {
+    fn set_property(&mut self, name: &str, value: &[f32]);
+
+    fn add_property(&mut self, name: &str, value: &[f32]) {
+        self.set_property(name, value);
+    }
+}
+
+impl PropertyTarget for HashMap<String, Vec<f32>> {
+    fn set_property(&mut self, name: &str, value: &[f32]) {
+        let entry = self.entry(name.to_string()).or_insert_with(Vec::new);
+        entry.clear();
+        entry.extend_from_slice(value);
+    }
+
+    fn add_property(&mut self, name: &str, value: &[f32]) {
+        let entry = self.entry(name.to_string()).or_insert_with(|| vec![0.0; value.len()]);
+        for (a, b) in entry.iter_mut().zip(value) {
+            *a += b;
+        }
+    }
+}
+
+// Stable reference to an animation in a library, animations are never removed so a handle
+// stays valid for the lifetime of the library that gave it out. A handle is only an index, one
+// that didn't come from the library finds nothing or an unrelated animation
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AnimationHandle(usize);
+
+impl AnimationHandle {
+    pub fn new(index: usize) -> AnimationHandle {
+        AnimationHandle(index)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
 pub trait AnimationLibrary<A: Animation> {
     fn get_animation(&self, index: usize) -> Option<&A>; 
+
+    fn get_by_handle(&self, handle: AnimationHandle) -> Option<&A> {
+        self.get_animation(handle.index())
+    }
+
+    fn find_animation(&self, _name: &str) -> Option<AnimationHandle> {
+        None
+    }
+
+    fn animation_name(&self, _handle: AnimationHandle) -> Option<&str> {
+        None
+    }
 }
\ No newline at end of file
@@ -0,0 +1,269 @@
+use glm::{self, Vec3};
+use pose::*;
+use mesh::{Mesh, MeshVertex};
+use skeleton::{Skeleton, error::MissingInvBindpose};
+use animation::traits::{Animation, Targets};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn from_point(point: Vec3) -> Aabb {
+        Aabb {
+            min: point,
+            max: point,
+        }
+    }
+
+    pub fn from_points(mut points: impl Iterator<Item = Vec3>) -> Option<Aabb> {
+        let mut aabb = Aabb::from_point(points.next()?);
+        for point in points {
+            aabb.extend(point);
+        }
+
+        Some(aabb)
+    }
+
+    pub fn extend(&mut self, point: Vec3) {
+        self.min = Vec3::new(self.min.x.min(point.x), self.min.y.min(point.y), self.min.z.min(point.z));
+        self.max = Vec3::new(self.max.x.max(point.x), self.max.y.max(point.y), self.max.z.max(point.z));
+    }
+
+    pub fn merge(&mut self, other: &Aabb) {
+        self.extend(other.min);
+        self.extend(other.max);
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.x >= self.min.x && point.y >= self.min.y && point.z >= self.min.z
+            && point.x <= self.max.x && point.y <= self.max.y && point.z <= self.max.z
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z),
+            Vec3::new(b.x, a.y, a.z),
+            Vec3::new(a.x, b.y, a.z),
+            Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z),
+            Vec3::new(b.x, a.y, b.z),
+            Vec3::new(a.x, b.y, b.z),
+            Vec3::new(b.x, b.y, b.z),
+        ]
+    }
+
+    // Bounds of the transformed box, may be larger than the transformed contents
+    pub fn transformed(&self, pose: &Pose) -> Aabb {
+        let corners = self.corners();
+        let mut aabb = Aabb::from_point(pose.transform_point(corners[0]));
+        for corner in corners.iter().skip(1) {
+            aabb.extend(pose.transform_point(*corner));
+        }
+
+        aabb
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    // Centers the sphere on the bounding box of the points
+    pub fn from_points(points: &[Vec3]) -> Option<BoundingSphere> {
+        let center = Aabb::from_points(points.iter().cloned())?.center();
+        let radius = points.iter().fold(0.0, |radius: f32, point| radius.max(glm::distance(&center, point)));
+
+        Some(BoundingSphere {
+            center,
+            radius,
+        })
+    }
+
+    pub fn transformed(&self, pose: &Pose) -> BoundingSphere {
+        let scale = pose.scale.x.abs().max(pose.scale.y.abs()).max(pose.scale.z.abs());
+
+        BoundingSphere {
+            center: pose.transform_point(self.center),
+            radius: self.radius * scale,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let radius = Vec3::new(self.radius, self.radius, self.radius);
+
+        Aabb {
+            min: self.center - radius,
+            max: self.center + radius,
+        }
+    }
+}
+
+// Bounds of the vertices influenced by each joint in that joint's space, None for joints that
+// influence no vertices
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JointBounds {
+    pub boxes: Vec<Option<Aabb>>,
+    pub spheres: Vec<Option<BoundingSphere>>,
+}
+
+impl JointBounds {
+    // Vertices are only added to joints that influence them with more than min_weight.
+    // The skeleton needs its inverse bind poses to move the vertices into joint space
+    pub fn from_mesh<V: MeshVertex>(mesh: &Mesh<V>, skeleton: &Skeleton, min_weight: f32) -> Result<JointBounds, MissingInvBindpose> {
+        let inv_bind_poses = skeleton.inv_bind_pose_ref();
+        let mut points: Vec<Vec<Vec3>> = vec![vec![]; skeleton.bone_count()];
+
+        for vertex in &mesh.vertices {
+            let position = vertex.position();
+            for (joint, weight) in vertex.joint_weights() {
+                if weight <= min_weight || joint >= points.len() {
+                    continue;
+                }
+
+                let inv_bind_pose = inv_bind_poses[joint].ok_or(MissingInvBindpose)?;
+                points[joint].push(inv_bind_pose.transform_point(position));
+            }
+        }
+
+        Ok(JointBounds {
+            boxes: points.iter().map(|x| Aabb::from_points(x.iter().cloned())).collect(),
+            spheres: points.iter().map(|x| BoundingSphere::from_points(x)).collect(),
+        })
+    }
+
+    // Combines the joint boxes with the skeletons current world poses, build_world_poses should
+    // be called before this. Returns None if no joint has both bounds and a world pose
+    pub fn world_aabb(&self, skeleton: &Skeleton) -> Option<Aabb> {
+        let mut bounds: Option<Aabb> = None;
+
+        for (aabb, pose) in self.boxes.iter().zip(skeleton.world_pose_ref()) {
+            let (aabb, pose) = match (aabb, pose) {
+                (Some(aabb), Some(pose)) => (aabb, pose),
+                _ => continue,
+            };
+
+            let transformed = aabb.transformed(pose);
+            match bounds.as_mut() {
+                Some(bounds) => bounds.merge(&transformed),
+                None => bounds = Some(transformed),
+            }
+        }
+
+        bounds
+    }
+
+    pub fn world_spheres<'a>(&'a self, skeleton: &'a Skeleton) -> impl Iterator<Item = Option<BoundingSphere>> + 'a {
+        self.spheres.iter().zip(skeleton.world_pose_ref()).map(|(sphere, pose)| match (sphere, pose) {
+            (Some(sphere), Some(pose)) => Some(sphere.transformed(pose)),
+            _ => None,
+        })
+    }
+
+    // Worst case bounds over every keyframe of the animation. Poses between keyframes are
+    // not sampled. The skeleton's local poses are restored afterwards
+    pub fn animation_aabb<A: Animation>(&self, skeleton: &mut Skeleton, animation: &A) -> Option<Aabb> {
+        let rest: Vec<Pose> = skeleton.pose_ref().to_vec();
+        let mut bounds: Option<Aabb> = None;
+
+        for frame in 0..animation.frames() {
+            let (poses, _) = match animation.get_frame(frame) {
+                Some(frame) => frame,
+                None => continue,
+            };
+
+            {
+                let local = skeleton.pose_ref_mut();
+                match animation.get_targets() {
+                    Targets::Specified(targets) => {
+                        for (pose, target) in poses.iter().zip(targets) {
+                            local[*target] = *pose;
+                        }
+                    }
+                    Targets::InOrder => {
+                        for (pose, target) in poses.iter().zip(local.iter_mut()) {
+                            *target = *pose;
+                        }
+                    }
+                }
+            }
+
+            skeleton.build_world_poses();
+            if let Some(aabb) = self.world_aabb(skeleton) {
+                match bounds.as_mut() {
+                    Some(bounds) => bounds.merge(&aabb),
+                    None => bounds = Some(aabb),
+                }
+            }
+        }
+
+        write_poses(&rest, skeleton.pose_ref_mut());
+        skeleton.build_world_poses();
+
+        bounds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mesh::Shape;
+
+    #[derive(Copy, Clone, Debug)]
+    struct TestVertex {
+        position: Vec3,
+        joint: usize,
+    }
+
+    impl MeshVertex for TestVertex {
+        fn position(&self) -> Vec3 {
+            self.position
+        }
+
+        fn set_position(&mut self, position: Vec3) {
+            self.position = position;
+        }
+
+        fn joint_weights(&self) -> Vec<(usize, f32)> {
+            vec![(self.joint, 1.0)]
+        }
+    }
+
+    #[test]
+    fn moved_joint_moves_bounds() {
+        let mut skeleton = Skeleton::from_tree_pose(vec![None, Some(0)], vec![Pose::pose_identity(), Pose::only_trans(0.0, 1.0, 0.0)]);
+        skeleton.build_inv_bind_poses();
+
+        let vertices = vec![
+            TestVertex { position: Vec3::new(-1.0, 0.0, 0.0), joint: 0 },
+            TestVertex { position: Vec3::new(1.0, 1.0, 0.0), joint: 1 },
+            TestVertex { position: Vec3::new(1.0, 2.0, 0.0), joint: 1 },
+        ];
+        let mesh = Mesh::new(vertices, vec![Shape::Triangle(0, 1, 2)]);
+        let bounds = JointBounds::from_mesh(&mesh, &skeleton, 0.0).unwrap();
+
+        skeleton.build_world_poses();
+        let aabb = bounds.world_aabb(&skeleton).unwrap();
+        assert!(aabb.min == Vec3::new(-1.0, 0.0, 0.0));
+        assert!(aabb.max == Vec3::new(1.0, 2.0, 0.0));
+
+        skeleton.joint_pose_mut(1).unwrap().translation.y = 3.0;
+        skeleton.build_world_poses();
+        let aabb = bounds.world_aabb(&skeleton).unwrap();
+        assert!(aabb.max == Vec3::new(1.0, 4.0, 0.0));
+    }
+}
@@ -0,0 +1,535 @@
+use skeleton::Skeleton;
+use animation;
+use pose::*;
+use math::*;
+use glm::Vec3;
+use std::error::Error;
+use std::fmt::{self, Display, Write};
+use std::fs::File;
+use std::io::{BufWriter, Read, Write as IoWrite};
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Channel {
+    Position(usize),
+    Rotation(usize),
+}
+
+impl Channel {
+    pub fn from_name(name: &str) -> Option<Channel> {
+        match name {
+            "Xposition" => Some(Channel::Position(0)),
+            "Yposition" => Some(Channel::Position(1)),
+            "Zposition" => Some(Channel::Position(2)),
+            "Xrotation" => Some(Channel::Rotation(0)),
+            "Yrotation" => Some(Channel::Rotation(1)),
+            "Zrotation" => Some(Channel::Rotation(2)),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Position(0) => "Xposition",
+            Channel::Position(1) => "Yposition",
+            Channel::Position(_) => "Zposition",
+            Channel::Rotation(0) => "Xrotation",
+            Channel::Rotation(1) => "Yrotation",
+            Channel::Rotation(_) => "Zrotation",
+        }
+    }
+}
+
+// A joint can have each position and rotation channel once
+const MAX_CHANNELS: usize = 6;
+// Over a day of motion at 120 frames per second
+const MAX_FRAMES: usize = 1 << 24;
+const RESERVED_FRAMES: usize = 1024;
+
+#[derive(Clone, Debug)]
+pub enum BvhError {
+    UnexpectedEnd,
+    UnexpectedToken { expected: &'static str, found: String },
+    InvalidNumber(String),
+    InvalidChannel(String),
+    MissingValues { frame: usize },
+    // Frame times have to be positive for the keyframe times to increase
+    InvalidFrameTime(f32),
+}
+
+impl Display for BvhError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BvhError::UnexpectedEnd => write!(f, "unexpected end of bvh file"),
+            BvhError::UnexpectedToken { expected, found } => write!(f, "expected {} but found {}", expected, found),
+            BvhError::InvalidNumber(token) => write!(f, "invalid number {}", token),
+            BvhError::InvalidChannel(token) => write!(f, "invalid channel {}", token),
+            BvhError::MissingValues { frame } => write!(f, "frame {} is missing channel values", frame),
+            BvhError::InvalidFrameTime(time) => write!(f, "frame time {} is not positive", time),
+        }
+    }
+}
+
+impl Error for BvhError {}
+
+// A parsed bvh file. The skeleton's rest poses are the joint offsets, the animation has a pose
+// for every joint
+#[derive(Clone)]
+pub struct Bvh {
+    pub skeleton: Skeleton,
+    pub animation: animation::Animation,
+    pub channels: Vec<Vec<Channel>>,
+    // Offset of the End Site of a joint, these don't become joints
+    pub end_sites: Vec<Option<Vec3>>,
+    pub frame_time: f32,
+}
+
+struct Tokens<'a> {
+    tokens: ::std::iter::Peekable<::std::str::SplitWhitespace<'a>>,
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Result<&'a str, BvhError> {
+        self.tokens.next().ok_or(BvhError::UnexpectedEnd)
+    }
+
+    fn peek(&mut self) -> Option<&'a str> {
+        self.tokens.peek().cloned()
+    }
+
+    fn expect(&mut self, expected: &'static str) -> Result<(), BvhError> {
+        let token = self.next()?;
+        match token == expected {
+            true => Ok(()),
+            false => Err(BvhError::UnexpectedToken { expected, found: token.to_string() }),
+        }
+    }
+
+    fn number(&mut self) -> Result<f32, BvhError> {
+        let token = self.next()?;
+        token.parse().map_err(|_| BvhError::InvalidNumber(token.to_string()))
+    }
+
+    // A whole number no larger than max
+    fn count(&mut self, max: usize) -> Result<usize, BvhError> {
+        let token = self.next()?;
+        match token.parse() {
+            Ok(count) if count <= max => Ok(count),
+            _ => Err(BvhError::InvalidNumber(token.to_string())),
+        }
+    }
+
+    fn vec3(&mut self) -> Result<Vec3, BvhError> {
+        Ok(Vec3::new(self.number()?, self.number()?, self.number()?))
+    }
+}
+
+struct Hierarchy {
+    tree: Vec<Option<usize>>,
+    names: Vec<String>,
+    offsets: Vec<Vec3>,
+    channels: Vec<Vec<Channel>>,
+    end_sites: Vec<Option<Vec3>>,
+}
+
+// Parses the contents of a joint after its name
+fn parse_joint(tokens: &mut Tokens, hierarchy: &mut Hierarchy, name: &str, parent: Option<usize>) -> Result<(), BvhError> {
+    let index = hierarchy.tree.len();
+    hierarchy.tree.push(parent);
+    hierarchy.names.push(name.to_string());
+    hierarchy.end_sites.push(None);
+
+    tokens.expect("{")?;
+    tokens.expect("OFFSET")?;
+    hierarchy.offsets.push(tokens.vec3()?);
+
+    let mut channels = vec![];
+    if tokens.peek() == Some("CHANNELS") {
+        tokens.next()?;
+        let count = tokens.count(MAX_CHANNELS)?;
+        for _ in 0..count {
+            let token = tokens.next()?;
+            channels.push(Channel::from_name(token).ok_or(BvhError::InvalidChannel(token.to_string()))?);
+        }
+    }
+    hierarchy.channels.push(channels);
+
+    loop {
+        match tokens.next()? {
+            "JOINT" => {
+                let name = tokens.next()?;
+                parse_joint(tokens, hierarchy, name, Some(index))?;
+            }
+            "End" => {
+                tokens.expect("Site")?;
+                tokens.expect("{")?;
+                tokens.expect("OFFSET")?;
+                hierarchy.end_sites[index] = Some(tokens.vec3()?);
+                tokens.expect("}")?;
+            }
+            "}" => return Ok(()),
+            token => return Err(BvhError::UnexpectedToken { expected: "JOINT, End or }", found: token.to_string() }),
+        }
+    }
+}
+
+fn frame_pose(offset: Vec3, channels: &[Channel], values: &[f32]) -> Pose {
+    let mut translation = offset;
+    let mut rotation = ::glm::quat_identity();
+
+    for (channel, value) in channels.iter().zip(values) {
+        match channel {
+            Channel::Position(axis) => translation[*axis] = *value,
+            Channel::Rotation(axis) => rotation = rotation * ::glm::quat_angle_axis(value.to_radians(), &unit_axis(*axis)),
+        }
+    }
+
+    Pose::without_scale(translation, rotation)
+}
+
+pub fn parse_bvh(text: &str) -> Result<Bvh, BvhError> {
+    let mut tokens = Tokens { tokens: text.split_whitespace().peekable() };
+    let mut hierarchy = Hierarchy {
+        tree: vec![],
+        names: vec![],
+        offsets: vec![],
+        channels: vec![],
+        end_sites: vec![],
+    };
+
+    tokens.expect("HIERARCHY")?;
+    while tokens.peek() == Some("ROOT") {
+        tokens.next()?;
+        let name = tokens.next()?;
+        parse_joint(&mut tokens, &mut hierarchy, name, None)?;
+    }
+
+    tokens.expect("MOTION")?;
+    tokens.expect("Frames:")?;
+    let frames = tokens.count(MAX_FRAMES)?;
+    tokens.expect("Frame")?;
+    tokens.expect("Time:")?;
+    let frame_time = tokens.number()?;
+    if frame_time.is_nan() || frame_time <= 0.0 {
+        return Err(BvhError::InvalidFrameTime(frame_time));
+    }
+
+    let joints = hierarchy.tree.len();
+    let values_per_frame: usize = hierarchy.channels.iter().map(|x| x.len()).sum();
+    // The frame count isn't trusted for the allocation, a short file fails with MissingValues
+    let mut animation = animation::Animation::with_capacity(joints, frames.min(RESERVED_FRAMES), None);
+    let mut values = Vec::with_capacity(values_per_frame);
+    let mut poses = Vec::with_capacity(joints);
+
+    for frame in 0..frames {
+        values.clear();
+        for _ in 0..values_per_frame {
+            match tokens.next() {
+                Ok(token) => values.push(token.parse().map_err(|_| BvhError::InvalidNumber(token.to_string()))?),
+                Err(_) => return Err(BvhError::MissingValues { frame }),
+            }
+        }
+
+        poses.clear();
+        let mut start = 0;
+        for joint in 0..joints {
+            let channels = &hierarchy.channels[joint];
+            poses.push(frame_pose(hierarchy.offsets[joint], channels, &values[start..start + channels.len()]));
+            start += channels.len();
+        }
+
+        animation.add_frame(&poses, frame as f32 * frame_time);
+    }
+
+    let rest = hierarchy.offsets.iter().map(|x| Pose::only_trans(x.x, x.y, x.z)).collect();
+    let mut skeleton = Skeleton::from_tree_pose(hierarchy.tree, rest);
+    for (i, name) in hierarchy.names.into_iter().enumerate() {
+        skeleton.set_joint_name(i, name);
+    }
+    skeleton.build_inv_bind_poses();
+
+    Ok(Bvh {
+        skeleton,
+        animation,
+        channels: hierarchy.channels,
+        end_sites: hierarchy.end_sites,
+        frame_time,
+    })
+}
+
+pub fn load_bvh(path: impl AsRef<Path>) -> Result<Bvh, Box<Error>> {
+    let mut text = String::new();
+    File::open(path)?.read_to_string(&mut text)?;
+
+    Ok(parse_bvh(&text)?)
+}
+
+// Channels used for written files, roots get positions as well as rotations
+pub const ROOT_CHANNELS: [Channel; 6] = [
+    Channel::Position(0), Channel::Position(1), Channel::Position(2),
+    Channel::Rotation(2), Channel::Rotation(0), Channel::Rotation(1),
+];
+pub const JOINT_CHANNELS: [Channel; 3] = [Channel::Rotation(2), Channel::Rotation(0), Channel::Rotation(1)];
+
+fn joint_channels(skeleton: &Skeleton, joint: usize) -> &'static [Channel] {
+    match skeleton.tree_ref()[joint] {
+        Some(_) => &JOINT_CHANNELS,
+        None => &ROOT_CHANNELS,
+    }
+}
+
+// Writes the joint and everything below it depth first, which is the order the motion values
+// have to follow, and appends the joints to order as they are written
+fn write_joint<W: Write>(out: &mut W, skeleton: &Skeleton, children: &[Vec<usize>], joint: usize, depth: usize, order: &mut Vec<usize>) -> fmt::Result {
+    let indent = "\t".repeat(depth);
+    let name = match skeleton.joint_name(joint) {
+        Some(name) => name.to_string(),
+        None => format!("joint_{}", joint),
+    };
+    let keyword = match skeleton.tree_ref()[joint] {
+        Some(_) => "JOINT",
+        None => "ROOT",
+    };
+    let offset = skeleton.pose_ref()[joint].translation;
+    let channels = joint_channels(skeleton, joint);
+    order.push(joint);
+
+    writeln!(out, "{}{} {}", indent, keyword, name)?;
+    writeln!(out, "{}{{", indent)?;
+    writeln!(out, "{}\tOFFSET {} {} {}", indent, offset.x, offset.y, offset.z)?;
+    write!(out, "{}\tCHANNELS {}", indent, channels.len())?;
+    for channel in channels {
+        write!(out, " {}", channel.name())?;
+    }
+    writeln!(out)?;
+
+    if children[joint].is_empty() {
+        writeln!(out, "{}\tEnd Site", indent)?;
+        writeln!(out, "{}\t{{", indent)?;
+        writeln!(out, "{}\t\tOFFSET 0 0 0", indent)?;
+        writeln!(out, "{}\t}}", indent)?;
+    }
+
+    for child in &children[joint] {
+        write_joint(out, skeleton, children, *child, depth + 1, order)?;
+    }
+
+    writeln!(out, "{}}}", indent)
+}
+
+fn channel_values(pose: &Pose, channels: &[Channel], out: &mut Vec<f32>) {
+    let mut order = [0; 3];
+    let mut axes = 0;
+    for channel in channels {
+        if let Channel::Rotation(axis) = channel {
+            order[axes] = *axis;
+            axes += 1;
+        }
+    }
+
+    let angles = quat_to_euler(&pose.rotation, &order);
+    let mut rotation = 0;
+    for channel in channels {
+        match channel {
+            Channel::Position(axis) => out.push(pose.translation[*axis]),
+            Channel::Rotation(_) => {
+                out.push(angles[rotation].to_degrees());
+                rotation += 1;
+            }
+        }
+    }
+}
+
+// Writes the skeleton and animation as bvh, the animation is sampled every frame_time seconds.
+// Joints that the animation doesn't target keep their rest pose, scale and the translation
+// of non root joints are not stored by bvh
+pub fn write_bvh<W: Write>(out: &mut W, skeleton: &Skeleton, clip: &animation::Animation, frame_time: f32) -> fmt::Result {
+    assert!(frame_time > 0.0);
+
+    let joints = skeleton.bone_count();
+    let mut children = vec![vec![]; joints];
+    for (i, parent) in skeleton.tree_ref().iter().enumerate() {
+        if let Some(parent) = parent {
+            children[*parent].push(i);
+        }
+    }
+
+    let mut order = Vec::with_capacity(joints);
+    writeln!(out, "HIERARCHY")?;
+    for root in (0..joints).filter(|x| skeleton.tree_ref()[*x].is_none()) {
+        write_joint(out, skeleton, &children, root, 0, &mut order)?;
+    }
+
+    let duration = clip.times.last().cloned().unwrap_or(0.0);
+    let frames = match clip.keyframes {
+        0 => 0,
+        _ => (duration / frame_time).round() as usize + 1,
+    };
+
+    writeln!(out, "MOTION")?;
+    writeln!(out, "Frames: {}", frames)?;
+    writeln!(out, "Frame Time: {}", frame_time)?;
+
+    let mut sampled = vec![];
+    let mut poses: Vec<Pose> = skeleton.pose_ref().to_vec();
+    let mut values = vec![];
+
+    for frame in 0..frames {
+        clip.sample_into(frame as f32 * frame_time, &mut sampled);
+        match &clip.targets {
+            Some(targets) => {
+                for (pose, target) in sampled.iter().zip(targets) {
+                    poses[*target] = *pose;
+                }
+            }
+            None => {
+                for (pose, target) in sampled.iter().zip(poses.iter_mut()) {
+                    *target = *pose;
+                }
+            }
+        }
+
+        values.clear();
+        for joint in order.iter().cloned() {
+            channel_values(&poses[joint], joint_channels(skeleton, joint), &mut values);
+        }
+
+        let line: Vec<String> = values.iter().map(|x| x.to_string()).collect();
+        writeln!(out, "{}", line.join(" "))?;
+    }
+
+    Ok(())
+}
+
+pub fn save_bvh(path: impl AsRef<Path>, skeleton: &Skeleton, clip: &animation::Animation, frame_time: f32) -> Result<(), Box<Error>> {
+    let mut text = String::new();
+    write_bvh(&mut text, skeleton, clip, frame_time)?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm;
+
+    const TEST_BVH: &str = "
+HIERARCHY
+ROOT Hips
+{
+    OFFSET 0 0 0
+    CHANNELS 6 Xposition Yposition Zposition Zrotation Xrotation Yrotation
+    JOINT Spine
+    {
+        OFFSET 0 1 0
+        CHANNELS 3 Xrotation Yrotation Zrotation
+        End Site
+        {
+            OFFSET 0 1 0
+        }
+    }
+}
+MOTION
+Frames: 2
+Frame Time: 0.5
+0 1 0 0 0 0 0 0 0
+1 1 0 90 0 0 0 90 0
+";
+
+    #[test]
+    fn parse_hierarchy_and_motion() {
+        let bvh = parse_bvh(TEST_BVH).unwrap();
+
+        assert!(bvh.skeleton.tree_ref() == &[None, Some(0)]);
+        assert!(bvh.skeleton.find_joint("Spine") == Some(1));
+        assert!(bvh.end_sites[1].is_some());
+        assert!(bvh.animation.keyframes == 2);
+        assert!(bvh.animation.times == vec![0.0, 0.5]);
+
+        let (poses, _) = bvh.animation.get_frame_and_time(1);
+        assert!(glm::distance(&poses[0].translation, &Vec3::new(1.0, 1.0, 0.0)) < 1e-6);
+
+        let x = glm::quat_rotate_vec3(&poses[0].rotation, &Vec3::new(1.0, 0.0, 0.0));
+        assert!(glm::distance(&x, &Vec3::new(0.0, 1.0, 0.0)) < 1e-5);
+
+        let z = glm::quat_rotate_vec3(&poses[1].rotation, &Vec3::new(0.0, 0.0, 1.0));
+        assert!(glm::distance(&z, &Vec3::new(1.0, 0.0, 0.0)) < 1e-5);
+        assert!(glm::distance(&poses[1].translation, &Vec3::new(0.0, 1.0, 0.0)) < 1e-6);
+    }
+
+    #[test]
+    fn write_and_parse() {
+        let bvh = parse_bvh(TEST_BVH).unwrap();
+        let mut text = String::new();
+        write_bvh(&mut text, &bvh.skeleton, &bvh.animation, bvh.frame_time).unwrap();
+        let reparsed = parse_bvh(&text).unwrap();
+
+        assert!(reparsed.skeleton.tree_ref() == bvh.skeleton.tree_ref());
+        assert!(reparsed.animation.keyframes == 2);
+
+        let (a, _) = bvh.animation.get_frame_and_time(1);
+        let (b, _) = reparsed.animation.get_frame_and_time(1);
+        for (a, b) in a.iter().zip(b) {
+            let point = Vec3::new(0.3, 0.5, 0.7);
+            let pa = a.transform_point(point);
+            let pb = b.transform_point(point);
+            assert!(glm::distance(&pa, &pb) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn write_non_depth_first_tree() {
+        // Depth first order is 0, 1, 3, 2
+        let tree = vec![None, Some(0), Some(0), Some(1)];
+        let rest: Vec<Pose> = (0..4).map(|i| Pose::only_trans(0.0, i as f32, 0.0)).collect();
+        let mut skeleton = Skeleton::from_tree_pose(tree, rest.clone());
+        for (i, name) in ["hips", "spine", "leg", "head"].iter().enumerate() {
+            skeleton.set_joint_name(i, *name);
+        }
+
+        let poses: Vec<Pose> = rest.iter().enumerate().map(|(i, pose)| {
+            let rotation = glm::quat_angle_axis(0.2 * (i + 1) as f32, &Vec3::new(0.0, 0.0, 1.0));
+            Pose::without_scale(pose.translation, rotation)
+        }).collect();
+        let mut clip = animation::Animation::new(4, None);
+        clip.add_frame(&poses, 0.0);
+
+        let mut text = String::new();
+        write_bvh(&mut text, &skeleton, &clip, 0.5).unwrap();
+        let reparsed = parse_bvh(&text).unwrap();
+        let (loaded, _) = reparsed.animation.get_frame_and_time(0);
+
+        for (joint, pose) in poses.iter().enumerate() {
+            let name = skeleton.joint_name(joint).unwrap();
+            let loaded = loaded[reparsed.skeleton.find_joint(name).unwrap()];
+            assert!(glm::dot(&loaded.rotation.coords, &pose.rotation.coords).abs() > 1.0 - 1e-5);
+        }
+    }
+
+    #[test]
+    fn reject_bad_frame_time() {
+        let text = TEST_BVH.replace("Frame Time: 0.5", "Frame Time: 0");
+        match parse_bvh(&text) {
+            Err(BvhError::InvalidFrameTime(_)) => {}
+            _ => panic!("expected an invalid frame time error"),
+        }
+    }
+
+    #[test]
+    fn reject_bad_counts() {
+        for (from, to) in &[("Frames: 2", "Frames: -1"), ("Frames: 2", "Frames: 99999999999"), ("Frames: 2", "Frames: 2.5"), ("CHANNELS 3", "CHANNELS 1000000")] {
+            match parse_bvh(&TEST_BVH.replace(from, to)) {
+                Err(BvhError::InvalidNumber(_)) => {}
+                _ => panic!("expected an invalid number error for {}", to),
+            }
+        }
+
+        // A frame count larger than the file only fails once the values run out
+        match parse_bvh(&TEST_BVH.replace("Frames: 2", "Frames: 1000000")) {
+            Err(BvhError::MissingValues { frame: 2 }) => {}
+            _ => panic!("expected missing values"),
+        }
+    }
+}
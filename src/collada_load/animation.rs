@@ -1,21 +1,25 @@
-use animation;
-use collada_parser::collada::{Skeleton, Animation};
-use std::error::Error;
-use std::fmt::{self, Display};
+use animation::{self, resample::*, clip::ClipRange, library::AniLibrary};
+use collada_parser::collada::{Skeleton, Animation, AnimationClip};
+use collada_load::error::ColladaLoadError;
 use pose::*;
 use math::*;
 
-#[derive(Copy, Clone, Debug)]
-pub struct AnimationLoadError;
+pub type AnimationLoadError = ColladaLoadError;
 
-impl Display for AnimationLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "failed to load animation")
-    }
+fn node_name(skeleton: &Skeleton, index: usize) -> String {
+    skeleton.nodes[index].name.clone()
 }
 
-impl Error for AnimationLoadError {}
+fn check_keyframes(skeleton: &Skeleton, index: usize, animation: &Animation) -> Result<(), AnimationLoadError> {
+    let (times, transformations) = (animation.sample_times.len(), animation.transformations.len());
+    if times == 0 || times != transformations {
+        return Err(ColladaLoadError::InvalidKeyframes { node: node_name(skeleton, index), index, times, transformations });
+    }
+
+    Ok(())
+}
 
+// Fails if the bones don't share the same sample times, use load_resampled_animation for those
 pub fn load_animation(skeleton: &Skeleton, ani: &[Animation]) -> Result<animation::Animation, AnimationLoadError> {
     let mut sample_times = None;
     let mut animations = vec![];
@@ -27,11 +31,12 @@ pub fn load_animation(skeleton: &Skeleton, ani: &[Animation]) -> Result<animatio
             None => continue,
         };
 
+        check_keyframes(skeleton, i, animation)?;
         if sample_times.is_none() {
             sample_times = Some(&animation.sample_times);
         }
         else if Some(&animation.sample_times) != sample_times {
-            return Err(AnimationLoadError);
+            return Err(ColladaLoadError::MismatchedSampleTimes { node: node_name(skeleton, i), index: i });
         }
 
         animations.push(&animation.transformations);
@@ -40,7 +45,7 @@ pub fn load_animation(skeleton: &Skeleton, ani: &[Animation]) -> Result<animatio
 
     let keyframes = match sample_times {
         Some(times) => times.len(),
-        None => return Err(AnimationLoadError), 
+        None => return Err(ColladaLoadError::NoAnimatedNodes),
     };
 
     let bones = targets.len();
@@ -66,4 +71,88 @@ pub fn load_animation(skeleton: &Skeleton, ani: &[Animation]) -> Result<animatio
     }
 
     Ok(animation)
-}
\ No newline at end of file
+}
+
+// Loads the animation with every bone curve interpolated onto a common timeline, so bones
+// may have different sample times. Times are moved to start at 0
+pub fn load_resampled_animation(skeleton: &Skeleton, ani: &[Animation], timeline: Timeline) -> Result<animation::Animation, AnimationLoadError> {
+    resampled_animation(skeleton, ani, timeline).map(|(animation, _)| animation)
+}
+
+// Also returns the document time of the first keyframe
+fn resampled_animation(skeleton: &Skeleton, ani: &[Animation], timeline: Timeline) -> Result<(animation::Animation, f32), AnimationLoadError> {
+    let mut curves = vec![];
+    let mut targets = vec![];
+
+    for (i, animation) in skeleton.animations(ani) {
+        let animation = match animation {
+            Some(animation) => animation,
+            None => continue,
+        };
+
+        check_keyframes(skeleton, i, animation)?;
+
+        let poses: Vec<Pose> = animation.transformations.iter().map(|x| {
+            let matrix = mat4_from_matrix4(x);
+            Pose::from_matrix(&matrix)
+        }).collect();
+
+        curves.push((animation.sample_times.as_slice(), poses));
+        targets.push(i);
+    }
+
+    let times = build_timeline(curves.iter().map(|x| x.0), timeline);
+    let start = match times.first() {
+        Some(start) => *start,
+        None => return Err(ColladaLoadError::NoAnimatedNodes),
+    };
+
+    let bones = targets.len();
+    let targets = match targets.len() == skeleton.nodes.len() {
+        false => Some(targets),
+        true => None,
+    };
+
+    let mut animation = animation::Animation::with_capacity(bones, times.len(), targets);
+    let mut poses = Vec::with_capacity(bones);
+    for time in &times {
+        poses.clear();
+        poses.extend(curves.iter().map(|(times, curve)| sample_curve(times, curve, *time)));
+
+        animation.add_frame(poses.as_ref(), *time - start);
+    }
+
+    Ok((animation, start))
+}
+
+// The clips of <library_animation_clips>, fails on a clip that ends before it starts
+pub fn clip_ranges(clips: &[AnimationClip]) -> Result<Vec<ClipRange>, AnimationLoadError> {
+    clips.iter().map(|x| {
+        if x.start.is_nan() || x.end.is_nan() || x.start > x.end {
+            return Err(ColladaLoadError::InvalidClip { clip: x.name.clone(), start: x.start, end: x.end });
+        }
+
+        Ok(ClipRange::new(x.name.clone(), x.start, x.end))
+    }).collect()
+}
+
+// Loads the whole timeline and cuts one animation per range out of it. The ranges are in
+// document time, either from clip_ranges or an external range list
+pub fn load_clip_animations(skeleton: &Skeleton, ani: &[Animation], ranges: &[ClipRange]) -> Result<Vec<(String, animation::Animation)>, AnimationLoadError> {
+    let (animation, start) = resampled_animation(skeleton, ani, Timeline::Union)?;
+
+    ranges.iter().map(|range| {
+        let invalid = AnimationLoadError::InvalidClip { clip: range.name.clone(), start: range.start, end: range.end };
+        let clip = animation.clip(range.start - start, range.end - start).ok_or(invalid)?;
+        Ok((range.name.clone(), clip))
+    }).collect()
+}
+
+// Same as load_clip_animations but adds the clips to the library under the ranges' names
+pub fn load_clips(skeleton: &Skeleton, ani: &[Animation], ranges: &[ClipRange], library: &mut AniLibrary<animation::Animation>) -> Result<(), AnimationLoadError> {
+    for (name, clip) in load_clip_animations(skeleton, ani, ranges)? {
+        library.add_named_animation(name, clip);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use collada_parser::collada::Collada;
+use coordinates::{CoordinateSystem, Conversion, UpAxis, Handedness};
+use collada_load::skeleton::try_load_skeleton;
+use collada_load::mesh::{VertexFromParts, load_skinned_mesh_with_sources};
+use collada_load::morph::{controller_base_mesh, load_document_morph_targets};
+use collada_load::skin::JointRemap;
+use collada_load::error::ColladaLoadError;
+use collada_load::animation::{load_animation, load_clip_animations, clip_ranges};
+use skeleton::Skeleton;
+use animation;
+use mesh::{self, MeshVertex};
+use std::error::Error;
+use std::path::Path;
+
+pub struct CharacterMesh<V> {
+    pub name: String,
+    pub mesh: mesh::Mesh<V>,
+    // Skeleton joint of every skin joint, the vertex weights of mesh are already remapped
+    pub remap: JointRemap,
+}
+
+// Everything needed to draw and animate a character from a single Collada document
+pub struct Character<V> {
+    // Has its inverse bind poses set from every skin
+    pub skeleton: Skeleton,
+    pub meshes: Vec<CharacterMesh<V>>,
+    // Every clip of <library_animation_clips>, or the whole timeline named after the skeleton's
+    // root node when the document has no clips
+    pub animations: Vec<(String, animation::Animation)>,
+    pub node_names: Vec<String>,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CharacterLoadOptions {
+    // Convention to convert the character into, the document's own is kept when None
+    pub coordinates: Option<CoordinateSystem>,
+}
+
+// The convention declared by the document's <up_axis> and <unit>, Collada is always right handed
+pub fn document_coordinates(document: &Collada) -> CoordinateSystem {
+    let up = UpAxis::from_collada(&document.asset.up_axis).unwrap_or(UpAxis::Y);
+    let meters = match document.asset.unit_meter > 0.0 {
+        true => document.asset.unit_meter,
+        false => 1.0,
+    };
+
+    CoordinateSystem::new(up, Handedness::Right, meters)
+}
+
+pub fn load_character<V: VertexFromParts + Copy>(path: impl AsRef<Path>) -> Result<Character<V>, Box<Error>> {
+    let document = Collada::parse(path.as_ref())?;
+    load_document_character(&document)
+}
+
+pub fn load_character_with_options<V: VertexFromParts + MeshVertex>(path: impl AsRef<Path>, options: &CharacterLoadOptions) -> Result<Character<V>, Box<Error>> {
+    let document = Collada::parse(path.as_ref())?;
+    let mut character = load_document_character(&document)?;
+
+    if let Some(target) = options.coordinates {
+        let conversion = Conversion::between(&document_coordinates(&document), &target);
+        if !conversion.is_identity() {
+            character.convert(&conversion);
+        }
+    }
+
+    Ok(character)
+}
+
+impl<V: MeshVertex> Character<V> {
+    pub fn convert(&mut self, conversion: &Conversion) {
+        conversion.convert_skeleton(&mut self.skeleton);
+        for mesh in self.meshes.iter_mut() {
+            conversion.convert_mesh(&mut mesh.mesh);
+        }
+        for (_, animation) in self.animations.iter_mut() {
+            conversion.convert_animation(animation);
+        }
+    }
+}
+
+fn load_document_character<V: VertexFromParts + Copy>(document: &Collada) -> Result<Character<V>, Box<Error>> {
+    let skeleton_source = document.skeletons.first().ok_or(ColladaLoadError::NoSkeleton)?;
+
+    let mut skeleton = try_load_skeleton(skeleton_source)?;
+    let node_names: Vec<String> = skeleton_source.nodes.iter().map(|x| x.name.clone()).collect();
+
+    let mut meshes = Vec::with_capacity(document.skins.len());
+    for skin in &document.skins {
+        let source = controller_base_mesh(document, &skin.source)
+            .ok_or(ColladaLoadError::MissingMesh(skin.source.clone()))?;
+
+        let remap = JointRemap::from_skin(&skeleton, skin)?;
+        remap.set_inv_bind_poses(&mut skeleton, skin)?;
+        let (mut mesh, sources) = load_skinned_mesh_with_sources(source, skin, &remap)?;
+        mesh.morph_targets = load_document_morph_targets(document, source, &sources)?;
+
+        meshes.push(CharacterMesh {
+            name: source.id.clone(),
+            mesh,
+            remap,
+        });
+    }
+
+    // Joints that no skin uses get their inverse bind pose from the rest pose
+    for i in 0..skeleton.bone_count() {
+        skeleton.joint_inv_bind_pose(i);
+    }
+
+    let mut animations = vec![];
+    let animated = skeleton_source.animations(&document.animations).any(|(_, animation)| animation.is_some());
+    if animated && !document.animation_clips.is_empty() {
+        let ranges = clip_ranges(&document.animation_clips)?;
+        animations = load_clip_animations(skeleton_source, &document.animations, &ranges)?;
+    }
+    else if animated {
+        let name = node_names.first().cloned().unwrap_or_default();
+        animations.push((name, load_animation(skeleton_source, &document.animations)?));
+    }
+
+    Ok(Character {
+        skeleton,
+        meshes,
+        animations,
+        node_names,
+    })
+}
@@ -0,0 +1,484 @@
+use super::character::{Character, load_character};
+use super::mesh::VertexFromParts;
+use super::animation::load_animation;
+use super::error::ColladaLoadError;
+use collada_parser::collada::{Collada, skin::JointWeight};
+use collada_parser::math::{Vector2, Vector3};
+use math::vec3_from_vector3;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Copy, Clone, Debug)]
+struct TestVertex {
+    joint: usize,
+}
+
+impl VertexFromParts for TestVertex {
+    fn from_parts(_: Vector3, _: Option<Vector2>, _: Option<Vector3>, weights: Option<&[JointWeight]>) -> Option<TestVertex> {
+        let joint = weights.and_then(|x| x.first()).map(|x| x.joint).unwrap_or(0);
+        Some(TestVertex { joint })
+    }
+}
+
+// Rejects every vertex right of x = 0.5
+#[derive(Copy, Clone, Debug)]
+struct LeftVertex;
+
+impl VertexFromParts for LeftVertex {
+    fn from_parts(vertex: Vector3, _: Option<Vector2>, _: Option<Vector3>, _: Option<&[JointWeight]>) -> Option<LeftVertex> {
+        match vec3_from_vector3(&vertex).x > 0.5 {
+            true => None,
+            false => Some(LeftVertex),
+        }
+    }
+}
+
+const IDENTITY: &str = "1 0 0 0 0 1 0 0 0 0 1 0 0 0 0 1";
+
+// Translation of (x, 1, 0) as a row major matrix
+fn moved(x: f32) -> String {
+    format!("1 0 0 {} 0 1 0 1 0 0 1 0 0 0 0 1", x)
+}
+
+// A matrix animation of node's transform with the node at x = time for every time. Only the
+// first count matrices are written so the times and transformations can be made to disagree
+fn animation_xml(node: &str, times: &[f32], count: usize) -> String {
+    let time_values: Vec<String> = times.iter().map(|x| x.to_string()).collect();
+    let matrices: Vec<String> = times.iter().take(count).map(|x| moved(*x)).collect();
+    let linear = vec!["LINEAR"; times.len()];
+
+    format!(r##"
+    <animation id="{node}_anim">
+      <source id="{node}_anim_input">
+        <float_array id="{node}_anim_input_array" count="{keys}">{times}</float_array>
+        <technique_common>
+          <accessor source="#{node}_anim_input_array" count="{keys}" stride="1">
+            <param name="TIME" type="float"/>
+          </accessor>
+        </technique_common>
+      </source>
+      <source id="{node}_anim_output">
+        <float_array id="{node}_anim_output_array" count="{values}">{matrices}</float_array>
+        <technique_common>
+          <accessor source="#{node}_anim_output_array" count="{count}" stride="16">
+            <param name="TRANSFORM" type="float4x4"/>
+          </accessor>
+        </technique_common>
+      </source>
+      <source id="{node}_anim_interpolation">
+        <Name_array id="{node}_anim_interpolation_array" count="{keys}">{linear}</Name_array>
+        <technique_common>
+          <accessor source="#{node}_anim_interpolation_array" count="{keys}" stride="1">
+            <param name="INTERPOLATION" type="name"/>
+          </accessor>
+        </technique_common>
+      </source>
+      <sampler id="{node}_anim_sampler">
+        <input semantic="INPUT" source="#{node}_anim_input"/>
+        <input semantic="OUTPUT" source="#{node}_anim_output"/>
+        <input semantic="INTERPOLATION" source="#{node}_anim_interpolation"/>
+      </sampler>
+      <channel source="#{node}_anim_sampler" target="{node}/transform"/>
+    </animation>"##,
+        node = node,
+        keys = times.len(),
+        times = time_values.join(" "),
+        values = count * 16,
+        count = count,
+        matrices = matrices.join(" "),
+        linear = linear.join(" "),
+    )
+}
+
+const CLIPS: &str = r##"
+  <library_animation_clips>
+    <animation_clip id="walk" name="walk" start="0" end="1">
+      <instance_animation url="#child_anim"/>
+    </animation_clip>
+    <animation_clip id="run" name="run" start="1" end="3">
+      <instance_animation url="#child_anim"/>
+    </animation_clip>
+  </library_animation_clips>"##;
+
+const TRIANGLES: &str = r##"<triangles count="1">
+          <input semantic="VERTEX" source="#body-mesh-vertices" offset="0"/>
+          <p>0 1 2</p>
+        </triangles>"##;
+
+// A triangle skinned to a two joint skeleton where the child moves along x over 3 seconds, cut
+// into two clips. The skin lists the joints in the opposite order to the skeleton
+fn test_document() -> String {
+    document_with_animations(&animation_xml("child", &[0.0, 1.0, 2.0, 3.0], 4), CLIPS)
+}
+
+fn document_with_animations(animations: &str, clips: &str) -> String {
+    format!(r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset>
+    <unit name="meter" meter="1"/>
+    <up_axis>Y_UP</up_axis>
+  </asset>
+  <library_animations>{animations}
+  </library_animations>{clips}
+  <library_geometries>
+    <geometry id="body-mesh" name="body">
+      <mesh>
+        <source id="body-mesh-positions">
+          <float_array id="body-mesh-positions-array" count="9">0 0 0 1 0 0 0 1 0</float_array>
+          <technique_common>
+            <accessor source="#body-mesh-positions-array" count="3" stride="3">
+              <param name="X" type="float"/>
+              <param name="Y" type="float"/>
+              <param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="body-mesh-vertices">
+          <input semantic="POSITION" source="#body-mesh-positions"/>
+        </vertices>
+        {triangles}
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_controllers>
+    <controller id="body-skin" name="body-skin">
+      <skin source="#body-mesh">
+        <bind_shape_matrix>{identity}</bind_shape_matrix>
+        <source id="body-skin-joints">
+          <Name_array id="body-skin-joints-array" count="2">child root</Name_array>
+          <technique_common>
+            <accessor source="#body-skin-joints-array" count="2" stride="1">
+              <param name="JOINT" type="name"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <source id="body-skin-bind_poses">
+          <float_array id="body-skin-bind_poses-array" count="32">{identity} {identity}</float_array>
+          <technique_common>
+            <accessor source="#body-skin-bind_poses-array" count="2" stride="16">
+              <param name="TRANSFORM" type="float4x4"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <source id="body-skin-weights">
+          <float_array id="body-skin-weights-array" count="1">1</float_array>
+          <technique_common>
+            <accessor source="#body-skin-weights-array" count="1" stride="1">
+              <param name="WEIGHT" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <joints>
+          <input semantic="JOINT" source="#body-skin-joints"/>
+          <input semantic="INV_BIND_MATRIX" source="#body-skin-bind_poses"/>
+        </joints>
+        <vertex_weights count="3">
+          <input semantic="JOINT" source="#body-skin-joints" offset="0"/>
+          <input semantic="WEIGHT" source="#body-skin-weights" offset="1"/>
+          <vcount>1 1 1</vcount>
+          <v>0 0 1 0 0 0</v>
+        </vertex_weights>
+      </skin>
+    </controller>
+  </library_controllers>
+  <library_visual_scenes>
+    <visual_scene id="scene" name="scene">
+      <node id="root" name="root" sid="root" type="JOINT">
+        <matrix sid="transform">{identity}</matrix>
+        <node id="child" name="child" sid="child" type="JOINT">
+          <matrix sid="transform">{rest}</matrix>
+        </node>
+      </node>
+      <node id="body" name="body" type="NODE">
+        <instance_controller url="#body-skin">
+          <skeleton>#root</skeleton>
+        </instance_controller>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+  <scene>
+    <instance_visual_scene url="#scene"/>
+  </scene>
+</COLLADA>
+"##, animations = animations, clips = clips, triangles = TRIANGLES, identity = IDENTITY, rest = moved(0.0))
+}
+
+// Writes the document to a file of its own so tests can run in parallel
+fn write_document(name: &str, document: &str) -> PathBuf {
+    let dir = env::temp_dir().join("animator_lib_collada_test");
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(format!("{}.dae", name));
+    fs::write(&path, document).unwrap();
+    path
+}
+
+fn load(name: &str, document: &str) -> Character<TestVertex> {
+    load_character(write_document(name, document)).unwrap()
+}
+
+fn load_error(name: &str, document: &str) -> ColladaLoadError {
+    load_error_as::<TestVertex>(name, document)
+}
+
+fn load_error_as<V: VertexFromParts + Copy>(name: &str, document: &str) -> ColladaLoadError {
+    match load_character::<V>(write_document(name, document)) {
+        Ok(_) => panic!("{} should not load", name),
+        Err(error) => error.downcast_ref::<ColladaLoadError>().expect("expected a ColladaLoadError").clone(),
+    }
+}
+
+fn with_primitive(primitive: &str) -> String {
+    test_document().replace(TRIANGLES, primitive)
+}
+
+#[test]
+fn character_clips() {
+    let character = load("clips", &test_document());
+
+    assert!(character.node_names == vec!["root".to_string(), "child".to_string()]);
+    assert!(character.animations.len() == 2);
+
+    let (name, walk) = &character.animations[0];
+    assert!(name == "walk");
+    assert!(walk.times == vec![0.0, 1.0]);
+
+    let (name, run) = &character.animations[1];
+    assert!(name == "run");
+    assert!(run.times == vec![0.0, 1.0, 2.0]);
+    let (poses, _) = run.get_frame_and_time(0);
+    assert!((poses[0].translation.x - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn character_skin_remap() {
+    let character = load("skin_remap", &test_document());
+
+    // The skin's joint 0 is the skeleton's joint 1
+    let mesh = &character.meshes[0];
+    assert!(mesh.remap.joints_ref() == &[1, 0]);
+    assert!(mesh.mesh.vertices.iter().map(|x| x.joint).collect::<Vec<_>>() == vec![1, 0, 1]);
+}
+
+#[test]
+fn character_inv_bind_poses() {
+    let document = test_document().replace(
+        &format!("{} {}</float_array>", IDENTITY, IDENTITY),
+        &format!("1 0 0 0 0 1 0 -1 0 0 1 0 0 0 0 1 {}</float_array>", IDENTITY),
+    );
+    let character = load("inv_bind_poses", &document);
+
+    // The skin's first bind pose belongs to the skeleton's second joint
+    let inv_bind_poses = character.skeleton.inv_bind_pose_ref();
+    assert!((inv_bind_poses[1].unwrap().translation.y + 1.0).abs() < 1e-6);
+    assert!(inv_bind_poses[0].unwrap().translation.y.abs() < 1e-6);
+}
+
+#[test]
+fn character_missing_joint() {
+    let document = test_document().replace("child root</Name_array>", "child hand</Name_array>");
+
+    match load_error("missing_joint", &document) {
+        ColladaLoadError::MissingJoint { mesh, joint } => assert!(mesh == "body-mesh" && joint == "hand"),
+        error => panic!("unexpected error {}", error),
+    }
+}
+
+#[test]
+fn character_invalid_clip() {
+    let document = test_document().replace(r#"name="run" start="1" end="3""#, r#"name="run" start="3" end="1""#);
+
+    match load_error("invalid_clip", &document) {
+        ColladaLoadError::InvalidClip { clip, start, end } => assert!(clip == "run" && start == 3.0 && end == 1.0),
+        error => panic!("unexpected error {}", error),
+    }
+}
+
+#[test]
+fn error_no_skeleton() {
+    let document = r##"<?xml version="1.0" encoding="utf-8"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset>
+    <up_axis>Y_UP</up_axis>
+  </asset>
+  <library_visual_scenes>
+    <visual_scene id="scene" name="scene"/>
+  </library_visual_scenes>
+  <scene>
+    <instance_visual_scene url="#scene"/>
+  </scene>
+</COLLADA>
+"##;
+
+    assert!(load_error("no_skeleton", document) == ColladaLoadError::NoSkeleton);
+}
+
+#[test]
+fn error_missing_mesh() {
+    let document = test_document().replace(r##"<skin source="#body-mesh">"##, r##"<skin source="#other-mesh">"##);
+    assert!(load_error("missing_mesh", &document) == ColladaLoadError::MissingMesh("other-mesh".to_string()));
+}
+
+#[test]
+fn error_missing_source() {
+    let normals = r##"<source id="body-mesh-normals">
+          <float_array id="body-mesh-normals-array" count="0"></float_array>
+          <technique_common>
+            <accessor source="#body-mesh-normals-array" count="0" stride="3">
+              <param name="X" type="float"/>
+              <param name="Y" type="float"/>
+              <param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="body-mesh-vertices">"##;
+    let document = with_primitive(r##"<triangles count="1">
+          <input semantic="VERTEX" source="#body-mesh-vertices" offset="0"/>
+          <input semantic="NORMAL" source="#body-mesh-normals" offset="1"/>
+          <p>0 0 1 0 2 0</p>
+        </triangles>"##).replace(r#"<vertices id="body-mesh-vertices">"#, normals);
+
+    let expected = ColladaLoadError::MissingSource { mesh: "body-mesh".to_string(), source: "normal" };
+    assert!(load_error("missing_source", &document) == expected);
+}
+
+#[test]
+fn error_index_out_of_range() {
+    let document = with_primitive(&TRIANGLES.replace("<p>0 1 2</p>", "<p>0 1 5</p>"));
+
+    let expected = ColladaLoadError::IndexOutOfRange { mesh: "body-mesh".to_string(), source: "vertex", index: 5, count: 3 };
+    assert!(load_error("index_out_of_range", &document) == expected);
+}
+
+#[test]
+fn error_rejected_vertex() {
+    let expected = ColladaLoadError::RejectedVertex { mesh: "body-mesh".to_string(), vertex: 1 };
+    assert!(load_error_as::<LeftVertex>("rejected_vertex", &test_document()) == expected);
+}
+
+#[test]
+fn error_unsupported_primitive() {
+    let document = with_primitive(r##"<linestrips count="1">
+          <input semantic="VERTEX" source="#body-mesh-vertices" offset="0"/>
+          <p>0 1 2</p>
+        </linestrips>"##);
+
+    let expected = ColladaLoadError::UnsupportedPrimitive { mesh: "body-mesh".to_string(), shape: 0 };
+    assert!(load_error("unsupported_primitive", &document) == expected);
+}
+
+#[test]
+fn error_degenerate_primitive() {
+    let document = with_primitive(r##"<polylist count="1">
+          <input semantic="VERTEX" source="#body-mesh-vertices" offset="0"/>
+          <vcount>2</vcount>
+          <p>0 1</p>
+        </polylist>"##);
+
+    let expected = ColladaLoadError::DegeneratePrimitive { mesh: "body-mesh".to_string(), shape: 0, points: 2 };
+    assert!(load_error("degenerate_primitive", &document) == expected);
+}
+
+#[test]
+fn error_morph_vertex_count() {
+    let smile = r##"<geometry id="smile-mesh" name="smile">
+      <mesh>
+        <source id="smile-mesh-positions">
+          <float_array id="smile-mesh-positions-array" count="6">0 0 0 1 0.5 0</float_array>
+          <technique_common>
+            <accessor source="#smile-mesh-positions-array" count="2" stride="3">
+              <param name="X" type="float"/>
+              <param name="Y" type="float"/>
+              <param name="Z" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <vertices id="smile-mesh-vertices">
+          <input semantic="POSITION" source="#smile-mesh-positions"/>
+        </vertices>
+        <lines count="1">
+          <input semantic="VERTEX" source="#smile-mesh-vertices" offset="0"/>
+          <p>0 1</p>
+        </lines>
+      </mesh>
+    </geometry>
+  </library_geometries>"##;
+    let morph = r##"<library_controllers>
+    <controller id="body-morph" name="body-morph">
+      <morph source="#body-mesh" method="NORMALIZED">
+        <source id="body-morph-targets">
+          <IDREF_array id="body-morph-targets-array" count="1">smile-mesh</IDREF_array>
+          <technique_common>
+            <accessor source="#body-morph-targets-array" count="1" stride="1">
+              <param name="IDREF" type="IDREF"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <source id="body-morph-weights">
+          <float_array id="body-morph-weights-array" count="1">0</float_array>
+          <technique_common>
+            <accessor source="#body-morph-weights-array" count="1" stride="1">
+              <param name="MORPH_WEIGHT" type="float"/>
+            </accessor>
+          </technique_common>
+        </source>
+        <targets>
+          <input semantic="MORPH_TARGET" source="#body-morph-targets"/>
+          <input semantic="MORPH_WEIGHT" source="#body-morph-weights"/>
+        </targets>
+      </morph>
+    </controller>"##;
+    let document = test_document()
+        .replace("</library_geometries>", smile)
+        .replace("<library_controllers>", morph);
+
+    let expected = ColladaLoadError::MorphVertexCount { target: "smile-mesh".to_string(), vertices: 2, expected: 3 };
+    assert!(load_error("morph_vertex_count", &document) == expected);
+}
+
+#[test]
+fn error_invalid_joint() {
+    let document = test_document().replace("<v>0 0 1 0 0 0</v>", "<v>0 0 1 0 5 0</v>");
+
+    let expected = ColladaLoadError::InvalidJoint { mesh: "body-mesh".to_string(), vertex: 2, joint: 5 };
+    assert!(load_error("invalid_joint", &document) == expected);
+}
+
+#[test]
+fn error_bind_pose_count() {
+    let document = test_document()
+        .replace(&format!(r#"count="32">{} {}</float_array>"#, IDENTITY, IDENTITY), &format!(r#"count="16">{}</float_array>"#, IDENTITY))
+        .replace(r##"source="#body-skin-bind_poses-array" count="2""##, r##"source="#body-skin-bind_poses-array" count="1""##);
+
+    let expected = ColladaLoadError::BindPoseCount { mesh: "body-mesh".to_string(), joints: 2, bind_poses: 1 };
+    assert!(load_error("bind_pose_count", &document) == expected);
+}
+
+#[test]
+fn error_no_animated_nodes() {
+    let path = write_document("no_animated_nodes", &document_with_animations("", ""));
+    let document = Collada::parse(&path).unwrap();
+
+    match load_animation(&document.skeletons[0], &document.animations) {
+        Err(ColladaLoadError::NoAnimatedNodes) => {}
+        _ => panic!("expected no animated nodes"),
+    }
+}
+
+#[test]
+fn error_mismatched_sample_times() {
+    let animations = animation_xml("child", &[0.0, 1.0, 2.0, 3.0], 4) + &animation_xml("root", &[0.0, 1.0, 2.0], 3);
+    let document = document_with_animations(&animations, "");
+
+    let expected = ColladaLoadError::MismatchedSampleTimes { node: "child".to_string(), index: 1 };
+    assert!(load_error("mismatched_sample_times", &document) == expected);
+}
+
+#[test]
+fn error_invalid_keyframes() {
+    let document = document_with_animations(&animation_xml("child", &[0.0, 1.0, 2.0, 3.0], 3), "");
+
+    let expected = ColladaLoadError::InvalidKeyframes { node: "child".to_string(), index: 1, times: 4, transformations: 3 };
+    assert!(load_error("invalid_keyframes", &document) == expected);
+}
@@ -0,0 +1,62 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use skeleton::error::SkeletonError;
+
+// Everything that can go wrong while turning a Collada document into skeletons, meshes and
+// animations. Meshes are named by their id and nodes by their name and index
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColladaLoadError {
+    // The document doesn't contain a skeleton
+    NoSkeleton,
+    // The skeleton's node parents don't form a tree
+    InvalidSkeleton(SkeletonError),
+    // The source mesh of a skin or a morph target isn't in the document
+    MissingMesh(String),
+    // A primitive uses an attribute that the mesh has no source for
+    MissingSource { mesh: String, source: &'static str },
+    IndexOutOfRange { mesh: String, source: &'static str, index: usize, count: usize },
+    // VertexFromParts returned None for the vertex built from this position index
+    RejectedVertex { mesh: String, vertex: usize },
+    UnsupportedPrimitive { mesh: String, shape: usize },
+    // A polygon, strip or fan with less than 3 points
+    DegeneratePrimitive { mesh: String, shape: usize, points: usize },
+    MorphVertexCount { target: String, vertices: usize, expected: usize },
+    // A joint of the skin on mesh that isn't in the skeleton
+    MissingJoint { mesh: String, joint: String },
+    // A vertex weight references a joint index past the end of the skin's joints
+    InvalidJoint { mesh: String, vertex: usize, joint: usize },
+    BindPoseCount { mesh: String, joints: usize, bind_poses: usize },
+    NoAnimatedNodes,
+    MismatchedSampleTimes { node: String, index: usize },
+    // A node's sample times and transformations have different lengths or are empty
+    InvalidKeyframes { node: String, index: usize, times: usize, transformations: usize },
+    // An animation clip that ends before it starts
+    InvalidClip { clip: String, start: f32, end: f32 },
+}
+
+impl Display for ColladaLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ColladaLoadError::*;
+
+        match self {
+            NoSkeleton => write!(f, "document does not contain a skeleton"),
+            InvalidSkeleton(error) => write!(f, "invalid skeleton: {}", error),
+            MissingMesh(id) => write!(f, "source mesh {} is missing", id),
+            MissingSource { mesh, source } => write!(f, "mesh {} has no {} source", mesh, source),
+            IndexOutOfRange { mesh, source, index, count } => write!(f, "mesh {} {} index {} is out of range, there are {}", mesh, source, index, count),
+            RejectedVertex { mesh, vertex } => write!(f, "mesh {} vertex {} was rejected by VertexFromParts", mesh, vertex),
+            UnsupportedPrimitive { mesh, shape } => write!(f, "mesh {} primitive {} has an unsupported type", mesh, shape),
+            DegeneratePrimitive { mesh, shape, points } => write!(f, "mesh {} primitive {} only has {} points", mesh, shape, points),
+            MorphVertexCount { target, vertices, expected } => write!(f, "morph target {} has {} vertices but the base mesh has {}", target, vertices, expected),
+            MissingJoint { mesh, joint } => write!(f, "joint {} of the skin on mesh {} is not in the skeleton", joint, mesh),
+            InvalidJoint { mesh, vertex, joint } => write!(f, "mesh {} vertex {} is weighted to skin joint {} which does not exist", mesh, vertex, joint),
+            BindPoseCount { mesh, joints, bind_poses } => write!(f, "the skin on mesh {} has {} joints but {} bind poses", mesh, joints, bind_poses),
+            NoAnimatedNodes => write!(f, "no node of the skeleton is animated"),
+            MismatchedSampleTimes { node, index } => write!(f, "node {} ({}) has different sample times to the other nodes", node, index),
+            InvalidKeyframes { node, index, times, transformations } => write!(f, "node {} ({}) has {} sample times and {} transformations", node, index, times, transformations),
+            InvalidClip { clip, start, end } => write!(f, "animation clip {} starts at {} but ends at {}", clip, start, end),
+        }
+    }
+}
+
+impl Error for ColladaLoadError {}
@@ -1,88 +1,173 @@
 use collada_parser::collada::{Mesh, Skin, mesh::primitive_elements::Shape, skin::JointWeight};
 use collada_parser::math::{Vector3, Vector2};
-use mesh;
+use mesh::{self, triangulate::*};
+use collada_load::skin::JointRemap;
+use math::vec3_from_vector3;
+use collada_load::error::ColladaLoadError;
 use std::collections::{HashMap, hash_map::Entry};
-use std::error::Error;
-use std::fmt::{self, Display};
 
-#[derive(Copy, Clone, Debug)]
-pub struct MeshLoadError;
-
-impl Display for MeshLoadError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "failed to load mesh")
-    }
-}
-
-impl Error for MeshLoadError {}
+pub type MeshLoadError = ColladaLoadError;
 
 pub trait VertexFromParts: Sized {
     fn from_parts(vertex: Vector3, tvertex: Option<Vector2>, normal: Option<Vector3>, weights: Option<&[JointWeight]>) -> Option<Self>;
 }
 
+pub type VertexSource = (usize, Option<usize>, Option<usize>);
+
+// Loads the mesh with the skin's weights as they are in the document, so the joint indices
+// index the skin's joints and not the skeleton
+#[deprecated(note = "use load_static_mesh, or load_skinned_mesh for weights in skeleton joint order")]
 pub fn load_mesh<V: VertexFromParts + Copy>(mesh: &Mesh, skin: Option<&Skin>) -> Result<mesh::Mesh<V>, MeshLoadError> {
+    load_mesh_with_weights(mesh, skin.map(|x| x.vertex_weights.as_slice())).map(|(mesh, _)| mesh)
+}
+
+// Loads the mesh without joint weights, skinned meshes go through load_skinned_mesh so that the
+// joints end up in skeleton order
+pub fn load_static_mesh<V: VertexFromParts + Copy>(mesh: &Mesh) -> Result<mesh::Mesh<V>, MeshLoadError> {
+    load_mesh_with_sources(mesh).map(|(mesh, _)| mesh)
+}
+
+// Also returns the (vertex, tex coord, normal) indices of the Collada mesh that each loaded vertex was built from
+pub fn load_mesh_with_sources<V: VertexFromParts + Copy>(mesh: &Mesh) -> Result<(mesh::Mesh<V>, Vec<VertexSource>), MeshLoadError> {
+    load_mesh_with_weights(mesh, None)
+}
+
+// Loads the mesh with the skin's vertex weights rewritten to skeleton joint indices
+pub fn load_skinned_mesh<V: VertexFromParts + Copy>(mesh: &Mesh, skin: &Skin, remap: &JointRemap) -> Result<mesh::Mesh<V>, MeshLoadError> {
+    load_skinned_mesh_with_sources(mesh, skin, remap).map(|(mesh, _)| mesh)
+}
+
+// Same as load_skinned_mesh but also returns the sources like load_mesh_with_sources
+pub fn load_skinned_mesh_with_sources<V: VertexFromParts + Copy>(mesh: &Mesh, skin: &Skin, remap: &JointRemap) -> Result<(mesh::Mesh<V>, Vec<VertexSource>), MeshLoadError> {
+    let weights = remap.remap_weights(&mesh.id, &skin.vertex_weights)?;
+    load_mesh_with_weights(mesh, Some(&weights))
+}
+
+fn load_mesh_with_weights<V: VertexFromParts + Copy>(mesh: &Mesh, vertex_weights: Option<&[Vec<JointWeight>]>) -> Result<(mesh::Mesh<V>, Vec<VertexSource>), MeshLoadError> {
     let mut vertices = vec![];
+    let mut sources = vec![];
     let mut shapes = vec![];
-    let mut indices: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+    let mut indices: HashMap<VertexSource, usize> = HashMap::new();
     let mut current_index = 0;
 
     {
-        let mut insert_update = |(vertex, tex, normal): (usize, Option<usize>, Option<usize>)| -> Result<usize, MeshLoadError> {
+        let mut insert_update = |(vertex, tex, normal): VertexSource| -> Result<usize, MeshLoadError> {
             let index = current_index;
             current_index += 1;
+            sources.push((vertex, tex, normal));
 
-            let vert = mesh.vertices[vertex];
-            let tex = tex.map(|x| mesh.tex_coords[x]);
-            let normal = normal.map(|x| mesh.normals[x]);
-            let weights = skin.map(|x| x.vertex_weights[vertex].as_slice());
+            let vert = *source_item(mesh, &mesh.vertices, "vertex", vertex)?;
+            let tex = match tex {
+                Some(x) => Some(*source_item(mesh, &mesh.tex_coords, "tex coord", x)?),
+                None => None,
+            };
+            let normal = match normal {
+                Some(x) => Some(*source_item(mesh, &mesh.normals, "normal", x)?),
+                None => None,
+            };
+            let weights = match vertex_weights {
+                Some(x) => Some(source_item(mesh, x, "skin weight", vertex)?.as_slice()),
+                None => None,
+            };
 
-            let vertex = match V::from_parts(vert, tex, normal, weights) {
-                Some(vertex) => vertex,
-                None => {
-                    return Err(MeshLoadError)
-                }
+            let loaded = match V::from_parts(vert, tex, normal, weights) {
+                Some(loaded) => loaded,
+                None => return Err(ColladaLoadError::RejectedVertex { mesh: mesh.id.clone(), vertex }),
             };
-            vertices.push(vertex);
+            vertices.push(loaded);
 
             Ok(index)
         };
 
-        for shape in &mesh.shapes {
+        let mut get_index = |(vertex, tex, normal): VertexSource| -> Result<usize, MeshLoadError> {
+            match indices.entry((vertex, tex, normal)) {
+                Entry::Vacant(entry) => Ok(*entry.insert(insert_update((vertex, tex, normal))?)),
+                Entry::Occupied(entry) => Ok(*entry.get()),
+            }
+        };
+
+        for (shape_index, shape) in mesh.shapes.iter().enumerate() {
             match shape {
+                Shape::Point(a) => {
+                    let a = get_index((a.0, a.1, a.2))?;
+
+                    shapes.push(mesh::Shape::Point(a));
+                },
                 Shape::Line(a, b) => {
-                    let a = match indices.entry((a.0, a.1, a.2)) {
-                        Entry::Vacant(entry) => *entry.insert(insert_update((a.0, a.1, a.2))?),
-                        Entry::Occupied(entry) => *entry.get()
-                    };
-                    let b = match indices.entry((b.0, b.1, b.2)) {
-                        Entry::Vacant(entry) => *entry.insert(insert_update((b.0, b.1, b.2))?),
-                        Entry::Occupied(entry) => *entry.get()
-                    };
+                    let a = get_index((a.0, a.1, a.2))?;
+                    let b = get_index((b.0, b.1, b.2))?;
 
                     shapes.push(mesh::Shape::Line(a, b));
                 },
                 Shape::Triangle(a, b, c) => {
-                    let a = match indices.entry((a.0, a.1, a.2)) {
-                        Entry::Vacant(entry) => *entry.insert(insert_update((a.0, a.1, a.2))?),
-                        Entry::Occupied(entry) => *entry.get()
-                    };
-                    let b = match indices.entry((b.0, b.1, b.2)) {
-                        Entry::Vacant(entry) => *entry.insert(insert_update((b.0, b.1, b.2))?),
-                        Entry::Occupied(entry) => *entry.get()
-                    };
-                    let c = match indices.entry((c.0, c.1, c.2)) {
-                        Entry::Vacant(entry) => *entry.insert(insert_update((c.0, c.1, c.2))?),
-                        Entry::Occupied(entry) => *entry.get()
-                    };
+                    let a = get_index((a.0, a.1, a.2))?;
+                    let b = get_index((b.0, b.1, b.2))?;
+                    let c = get_index((c.0, c.1, c.2))?;
 
                     shapes.push(mesh::Shape::Triangle(a, b, c));
                 },
-                _ => {}
+                Shape::Polygon(points) => {
+                    let points: Vec<_> = points.iter().map(|x| (x.0, x.1, x.2)).collect();
+                    if points.len() < 3 {
+                        return Err(ColladaLoadError::DegeneratePrimitive { mesh: mesh.id.clone(), shape: shape_index, points: points.len() });
+                    }
+
+                    let positions = points.iter()
+                        .map(|x| source_item(mesh, &mesh.vertices, "vertex", x.0).map(vec3_from_vector3))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let triangles = triangulate_polygon(&positions);
+                    push_triangles(&mut shapes, &points, &triangles, &mut get_index)?;
+                },
+                Shape::TriStrip(points) => {
+                    let points: Vec<_> = points.iter().map(|x| (x.0, x.1, x.2)).collect();
+                    if points.len() < 3 {
+                        return Err(ColladaLoadError::DegeneratePrimitive { mesh: mesh.id.clone(), shape: shape_index, points: points.len() });
+                    }
+
+                    push_triangles(&mut shapes, &points, &strip_triangles(points.len()), &mut get_index)?;
+                },
+                Shape::TriFan(points) => {
+                    let points: Vec<_> = points.iter().map(|x| (x.0, x.1, x.2)).collect();
+                    if points.len() < 3 {
+                        return Err(ColladaLoadError::DegeneratePrimitive { mesh: mesh.id.clone(), shape: shape_index, points: points.len() });
+                    }
+
+                    push_triangles(&mut shapes, &points, &fan_triangles(points.len()), &mut get_index)?;
+                },
+                // Anything else can't be represented in a mesh::Mesh so it's better to fail than to
+                // silently drop part of the mesh
+                _ => return Err(ColladaLoadError::UnsupportedPrimitive { mesh: mesh.id.clone(), shape: shape_index }),
             }
         }
     }
-    Ok(mesh::Mesh {
-        vertices,
-        shapes,
-    })
-} 
\ No newline at end of file
+    Ok((mesh::Mesh::new(vertices, shapes), sources))
+}
+
+// Looks up an item of one of the mesh's sources, naming the mesh and source on failure
+fn source_item<'a, T>(mesh: &Mesh, items: &'a [T], source: &'static str, index: usize) -> Result<&'a T, MeshLoadError> {
+    if items.is_empty() {
+        return Err(ColladaLoadError::MissingSource { mesh: mesh.id.clone(), source });
+    }
+
+    items.get(index).ok_or_else(|| ColladaLoadError::IndexOutOfRange { mesh: mesh.id.clone(), source, index, count: items.len() })
+}
+
+// Pushes triangles made out of indices into points, skipping any that collapse to a line
+fn push_triangles<F>(shapes: &mut Vec<mesh::Shape>, points: &[VertexSource], triangles: &[[usize; 3]], get_index: &mut F) -> Result<(), MeshLoadError>
+where
+    F: FnMut(VertexSource) -> Result<usize, MeshLoadError>,
+{
+    for triangle in triangles {
+        let a = get_index(points[triangle[0]])?;
+        let b = get_index(points[triangle[1]])?;
+        let c = get_index(points[triangle[2]])?;
+
+        if a == b || b == c || a == c {
+            continue;
+        }
+
+        shapes.push(mesh::Shape::Triangle(a, b, c));
+    }
+
+    Ok(())
+}
@@ -1,3 +1,12 @@
+pub mod error;
 pub mod skeleton;
 pub mod mesh;
-pub mod animation;
\ No newline at end of file
+pub mod animation;
+pub mod morph;
+pub mod skin;
+pub mod character;
+#[cfg(test)]
+mod collada_tests;
+
+pub use self::error::ColladaLoadError;
+pub use self::character::{load_character, load_character_with_options};
@@ -0,0 +1,68 @@
+use collada_parser::collada::{Collada, Mesh};
+use collada_load::mesh::{MeshLoadError, VertexSource};
+use collada_load::error::ColladaLoadError;
+use mesh::morph::MorphTarget;
+use math::vec3_from_vector3;
+use glm::Vec3;
+
+// Vertices that move less than this are left out of the sparse morph target
+const MORPH_EPSILON: f32 = 1e-6;
+
+// Builds a morph target for a mesh loaded with load_mesh_with_sources. The targets of a <morph>
+// controller are full meshes that share the vertex layout of the base mesh, so the deltas are
+// found per loaded vertex from the Collada indices it was built from
+pub fn load_morph_target(name: &str, base: &Mesh, target: &Mesh, sources: &[VertexSource]) -> Result<MorphTarget, MeshLoadError> {
+    if base.vertices.len() != target.vertices.len() {
+        return Err(ColladaLoadError::MorphVertexCount { target: name.to_string(), vertices: target.vertices.len(), expected: base.vertices.len() });
+    }
+
+    let has_normals = base.normals.len() == target.normals.len();
+    let zero = Vec3::new(0.0, 0.0, 0.0);
+    let mut base_parts = Vec::with_capacity(sources.len());
+    let mut target_parts = Vec::with_capacity(sources.len());
+
+    for (vertex, _, normal) in sources {
+        let normals = match (normal, has_normals) {
+            (Some(normal), true) => (vec3_from_vector3(&base.normals[*normal]), vec3_from_vector3(&target.normals[*normal])),
+            _ => (zero, zero),
+        };
+
+        base_parts.push((vec3_from_vector3(&base.vertices[*vertex]), normals.0));
+        target_parts.push((vec3_from_vector3(&target.vertices[*vertex]), normals.1));
+    }
+
+    Ok(MorphTarget::from_difference(name, &base_parts, &target_parts, MORPH_EPSILON))
+}
+
+// Loads every target of a <morph> controller in order, the names are used to look up weights
+pub fn load_morph_targets<'a>(base: &Mesh, targets: impl IntoIterator<Item = (&'a str, &'a Mesh)>, sources: &[VertexSource]) -> Result<Vec<MorphTarget>, MeshLoadError> {
+    targets.into_iter().map(|(name, target)| load_morph_target(name, base, target, sources)).collect()
+}
+
+// The mesh a skin or morph controller is built on, a skin's source can be a <morph> controller
+// in which case its base mesh is used
+pub fn controller_base_mesh<'a>(document: &'a Collada, source: &str) -> Option<&'a Mesh> {
+    let source = match document.morphs.iter().find(|x| x.id == source) {
+        Some(morph) => morph.source.as_str(),
+        None => source,
+    };
+
+    document.meshes.iter().find(|x| x.id == source)
+}
+
+// Loads the targets of the <morph> controller built on base, no targets if there isn't one
+pub fn load_document_morph_targets(document: &Collada, base: &Mesh, sources: &[VertexSource]) -> Result<Vec<MorphTarget>, MeshLoadError> {
+    let morph = match document.morphs.iter().find(|x| x.source == base.id) {
+        Some(morph) => morph,
+        None => return Ok(vec![]),
+    };
+
+    let targets = morph.targets.iter().map(|id| {
+        document.meshes.iter()
+            .find(|x| x.id == *id)
+            .map(|x| (id.as_str(), x))
+            .ok_or_else(|| ColladaLoadError::MissingMesh(id.clone()))
+    }).collect::<Result<Vec<_>, _>>()?;
+
+    load_morph_targets(base, targets, sources)
+}
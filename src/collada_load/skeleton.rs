@@ -1,9 +1,19 @@
 use collada_parser::collada::{self, Skin};
-use skeleton::{Skeleton, error::*};
+use skeleton::{Skeleton, validate_tree, error::MissingInvBindpose};
+use collada_load::skin::JointRemap;
+use collada_load::error::ColladaLoadError;
 use math::*;
 use pose::*;
 
+// Panics if the node parents don't form a tree, see try_load_skeleton
 pub fn load_skeleton(skeleton: &collada::Skeleton) -> Skeleton {
+    match try_load_skeleton(skeleton) {
+        Ok(skeleton) => skeleton,
+        Err(error) => panic!("{}", error),
+    }
+}
+
+pub fn try_load_skeleton(skeleton: &collada::Skeleton) -> Result<Skeleton, ColladaLoadError> {
     let mut tree = Vec::with_capacity(skeleton.nodes.len());
     let mut poses = Vec::with_capacity(skeleton.nodes.len());
 
@@ -16,12 +26,32 @@ pub fn load_skeleton(skeleton: &collada::Skeleton) -> Skeleton {
         poses.push(pose);
     }
 
-    Skeleton::from_tree_pose(tree, poses)
+    validate_tree(&tree).map_err(ColladaLoadError::InvalidSkeleton)?;
+
+    let mut loaded = Skeleton::from_tree_pose(tree, poses);
+    for (i, node) in skeleton.nodes.iter().enumerate() {
+        loaded.set_joint_name(i, node.name.clone());
+    }
+
+    Ok(loaded)
 }
 
+// Same as bind_skin_skeleton without the remap, the error only says that something was missing
+#[deprecated(note = "use bind_skin_skeleton, which returns the remap for load_skinned_mesh")]
 pub fn set_bind_poses_skeleton(skeleton: &mut Skeleton, skin: &Skin) -> Result<(), MissingInvBindpose> {
-    skeleton.set_inv_bind_pose_iter(skin.bind_poses.iter().map(|x| {
-        let matrix = mat4_from_matrix4(x);
-        Pose::from_matrix(&matrix)
-    }))
-} 
\ No newline at end of file
+    bind_skin_skeleton(skeleton, skin).map(|_| ()).map_err(|_| MissingInvBindpose)
+}
+
+// Sets the inverse bind poses of the joints used by the skin, matching them by name, joints
+// the skin doesn't use get one built from the rest pose. Returns the remap needed to load the
+// skin's meshes in skeleton joint order
+pub fn bind_skin_skeleton(skeleton: &mut Skeleton, skin: &Skin) -> Result<JointRemap, ColladaLoadError> {
+    let remap = JointRemap::from_skin(skeleton, skin)?;
+    remap.set_inv_bind_poses(skeleton, skin)?;
+
+    for i in 0..skeleton.bone_count() {
+        skeleton.joint_inv_bind_pose(i);
+    }
+
+    Ok(remap)
+}
@@ -0,0 +1,86 @@
+use collada_parser::collada::{Skin, skin::JointWeight};
+use skeleton::Skeleton;
+use math::*;
+use pose::*;
+use collada_load::error::ColladaLoadError;
+
+// Maps the joint indices of a Collada skin, which only lists the joints it uses in its own
+// order, to joint indices of a skeleton
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointRemap {
+    joints: Vec<usize>,
+}
+
+impl JointRemap {
+    pub fn new(joints: Vec<usize>) -> JointRemap {
+        JointRemap { joints }
+    }
+
+    // Matches the skin's joints to the skeleton's joints by name
+    pub fn from_skin(skeleton: &Skeleton, skin: &Skin) -> Result<JointRemap, ColladaLoadError> {
+        let joints = skin.joints.iter().map(|name| {
+            skeleton.find_joint(name).ok_or_else(|| ColladaLoadError::MissingJoint { mesh: skin.source.clone(), joint: name.clone() })
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(JointRemap::new(joints))
+    }
+
+    pub fn skeleton_joint(&self, skin_joint: usize) -> Option<usize> {
+        self.joints.get(skin_joint).cloned()
+    }
+
+    pub fn joints_ref(&self) -> &[usize] {
+        &self.joints
+    }
+
+    // The vertex weights of the skin on mesh with the joints in skeleton order
+    pub fn remap_weights(&self, mesh: &str, weights: &[Vec<JointWeight>]) -> Result<Vec<Vec<JointWeight>>, ColladaLoadError> {
+        weights.iter().enumerate().map(|(vertex, weights)| {
+            weights.iter().map(|x| {
+                let invalid = || ColladaLoadError::InvalidJoint { mesh: mesh.to_string(), vertex, joint: x.joint };
+                let joint = self.skeleton_joint(x.joint).ok_or_else(invalid)?;
+                Ok(JointWeight { joint, weight: x.weight })
+            }).collect()
+        }).collect()
+    }
+
+    // Writes the skin's inverse bind poses to the skeleton joints they belong to, other joints
+    // are left unchanged
+    pub fn set_inv_bind_poses(&self, skeleton: &mut Skeleton, skin: &Skin) -> Result<(), ColladaLoadError> {
+        if skin.bind_poses.len() != self.joints.len() {
+            return Err(ColladaLoadError::BindPoseCount { mesh: skin.source.clone(), joints: self.joints.len(), bind_poses: skin.bind_poses.len() });
+        }
+
+        for (joint, bind_pose) in self.joints.iter().zip(skin.bind_poses.iter()) {
+            let matrix = mat4_from_matrix4(bind_pose);
+            skeleton.set_joint_inv_bind_pose(*joint, Pose::from_matrix(&matrix));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remap_vertex_weights() {
+        let remap = JointRemap::new(vec![3, 1]);
+        let weights = vec![
+            vec![JointWeight { joint: 0, weight: 0.25 }, JointWeight { joint: 1, weight: 0.75 }],
+            vec![JointWeight { joint: 1, weight: 1.0 }],
+        ];
+
+        let remapped = remap.remap_weights("body", &weights).unwrap();
+        assert!(remapped[0][0].joint == 3 && remapped[0][0].weight == 0.25);
+        assert!(remapped[0][1].joint == 1);
+        assert!(remapped[1][0].joint == 1);
+
+        let invalid = vec![vec![], vec![JointWeight { joint: 2, weight: 1.0 }]];
+        match remap.remap_weights("body", &invalid) {
+            Err(ColladaLoadError::InvalidJoint { mesh, vertex: 1, joint: 2 }) => assert!(mesh == "body"),
+            _ => panic!("expected an invalid joint error"),
+        }
+    }
+}
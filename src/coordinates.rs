@@ -0,0 +1,225 @@
+use glm::{self, Mat3, Vec3};
+use pose::*;
+use skeleton::Skeleton;
+use animation;
+use mesh::{Mesh, MeshVertex, Shape};
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum UpAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    // Parses the value of a Collada <up_axis>
+    pub fn from_collada(axis: &str) -> Option<UpAxis> {
+        match axis.trim() {
+            "X_UP" => Some(UpAxis::X),
+            "Y_UP" => Some(UpAxis::Y),
+            "Z_UP" => Some(UpAxis::Z),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Handedness {
+    Right,
+    Left,
+}
+
+// The axis conventions and units of an asset. The right and up axes follow Collada, for
+// X_UP the right axis is -Y, for Z_UP the axis towards the viewer is -Y
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CoordinateSystem {
+    pub up: UpAxis,
+    pub handedness: Handedness,
+    pub meters_per_unit: f32,
+}
+
+impl CoordinateSystem {
+    pub fn new(up: UpAxis, handedness: Handedness, meters_per_unit: f32) -> CoordinateSystem {
+        assert!(meters_per_unit > 0.0);
+
+        CoordinateSystem {
+            up,
+            handedness,
+            meters_per_unit,
+        }
+    }
+
+    // Right handed, Y up and in meters, the convention used by glTF
+    pub fn y_up_meters() -> CoordinateSystem {
+        CoordinateSystem::new(UpAxis::Y, Handedness::Right, 1.0)
+    }
+
+    // Rotation (or reflection) from this system's axes to (right, up, towards viewer)
+    fn to_canonical(&self) -> Mat3 {
+        let mut basis = match self.up {
+            UpAxis::X => glm::mat3(
+                0.0, -1.0, 0.0,
+                1.0, 0.0, 0.0,
+                0.0, 0.0, 1.0,
+            ),
+            UpAxis::Y => glm::mat3(
+                1.0, 0.0, 0.0,
+                0.0, 1.0, 0.0,
+                0.0, 0.0, 1.0,
+            ),
+            UpAxis::Z => glm::mat3(
+                1.0, 0.0, 0.0,
+                0.0, 0.0, 1.0,
+                0.0, -1.0, 0.0,
+            ),
+        };
+
+        if self.handedness == Handedness::Left {
+            for column in 0..3 {
+                basis[(2, column)] = -basis[(2, column)];
+            }
+        }
+
+        basis
+    }
+}
+
+impl Default for CoordinateSystem {
+    fn default() -> CoordinateSystem {
+        CoordinateSystem::y_up_meters()
+    }
+}
+
+// Converts values from one coordinate system to another. Transforms are conjugated by the
+// change of basis so skeletons, bind poses and animations stay consistent with each other
+#[derive(Copy, Clone, Debug)]
+pub struct Conversion {
+    basis: Mat3,
+    scale: f32,
+}
+
+impl Conversion {
+    pub fn between(from: &CoordinateSystem, to: &CoordinateSystem) -> Conversion {
+        Conversion {
+            basis: glm::transpose(&to.to_canonical()) * from.to_canonical(),
+            scale: from.meters_per_unit / to.meters_per_unit,
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.scale == 1.0 && self.basis == Mat3::identity()
+    }
+
+    // True when the conversion changes handedness, triangle winding has to be flipped
+    pub fn is_mirrored(&self) -> bool {
+        self.basis.determinant() < 0.0
+    }
+
+    pub fn point(&self, point: Vec3) -> Vec3 {
+        self.basis * point * self.scale
+    }
+
+    pub fn direction(&self, direction: Vec3) -> Vec3 {
+        self.basis * direction
+    }
+
+    pub fn pose(&self, pose: &Pose) -> Pose {
+        let rotation = self.basis * glm::quat_to_mat3(&pose.rotation) * glm::transpose(&self.basis);
+        let scale = self.basis * pose.scale;
+
+        Pose {
+            translation: self.point(pose.translation),
+            rotation: glm::quat_normalize(&glm::mat3_to_quat(&rotation)),
+            // The basis only permutes and negates axes so the scale stays axis aligned
+            scale: Vec3::new(scale.x.abs(), scale.y.abs(), scale.z.abs()),
+        }
+    }
+
+    pub fn convert_poses(&self, poses: &mut [Pose]) {
+        for pose in poses.iter_mut() {
+            *pose = self.pose(pose);
+        }
+    }
+
+    // Converts the rest poses and inverse bind poses, world poses have to be rebuilt
+    pub fn convert_skeleton(&self, skeleton: &mut Skeleton) {
+        self.convert_poses(skeleton.pose_ref_mut());
+
+        for pose in skeleton.inv_bind_pose_ref_mut().iter_mut() {
+            if let Some(pose) = pose {
+                *pose = self.pose(pose);
+            }
+        }
+
+        skeleton.reset_world_poses();
+    }
+
+    pub fn convert_animation(&self, animation: &mut animation::Animation) {
+        self.convert_poses(&mut animation.poses);
+    }
+
+    pub fn convert_mesh<V: MeshVertex>(&self, mesh: &mut Mesh<V>) {
+        for vertex in mesh.vertices.iter_mut() {
+            let position = self.point(vertex.position());
+            vertex.set_position(position);
+
+            if let Some(normal) = vertex.normal() {
+                vertex.set_normal(self.direction(normal));
+            }
+        }
+
+        for target in mesh.morph_targets.iter_mut() {
+            for delta in target.deltas.iter_mut() {
+                delta.position = self.basis * delta.position * self.scale;
+                delta.normal = self.direction(delta.normal);
+            }
+        }
+
+        if self.is_mirrored() {
+            for shape in mesh.shapes.iter_mut() {
+                if let Shape::Triangle(a, b, c) = *shape {
+                    *shape = Shape::Triangle(a, c, b);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn z_up_centimeters_to_y_up_meters() {
+        let from = CoordinateSystem::new(UpAxis::Z, Handedness::Right, 0.01);
+        let conversion = Conversion::between(&from, &CoordinateSystem::y_up_meters());
+
+        let up = conversion.point(Vec3::new(0.0, 0.0, 100.0));
+        assert!(glm::distance(&up, &Vec3::new(0.0, 1.0, 0.0)) < 1e-6);
+        let forward = conversion.point(Vec3::new(0.0, 100.0, 0.0));
+        assert!(glm::distance(&forward, &Vec3::new(0.0, 0.0, -1.0)) < 1e-6);
+        assert!(!conversion.is_mirrored());
+
+        // A turn around the up axis stays a turn around the up axis
+        let pose = Pose::only_rot(glm::quat_angle_axis(1.0, &Vec3::new(0.0, 0.0, 1.0)));
+        let converted = conversion.pose(&pose);
+        let expected = glm::quat_angle_axis(1.0, &Vec3::new(0.0, 1.0, 0.0));
+        assert!(glm::quat_dot(&converted.rotation, &expected).abs() > 0.9999);
+
+        // Converting a transformed point is the same as transforming the converted point
+        let pose = Pose::without_scale(Vec3::new(10.0, 20.0, 30.0), glm::quat_angle_axis(0.5, &glm::normalize(&Vec3::new(1.0, 2.0, 3.0))));
+        let point = Vec3::new(5.0, -3.0, 2.0);
+        let a = conversion.point(pose.transform_point(point));
+        let b = conversion.pose(&pose).transform_point(conversion.point(point));
+        assert!(glm::distance(&a, &b) < 1e-4);
+    }
+
+    #[test]
+    fn handedness_mirrors() {
+        let from = CoordinateSystem::new(UpAxis::Y, Handedness::Left, 1.0);
+        let conversion = Conversion::between(&from, &CoordinateSystem::y_up_meters());
+
+        assert!(conversion.is_mirrored());
+        assert!(conversion.point(Vec3::new(1.0, 2.0, 3.0)) == Vec3::new(1.0, 2.0, -3.0));
+    }
+}
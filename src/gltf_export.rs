@@ -0,0 +1,547 @@
+use gltf_load::document::*;
+use skeleton::Skeleton;
+use mesh::{self, MeshVertex};
+use animation::traits::{Animation, Targets};
+use pose::*;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use serde_json;
+
+#[derive(Clone, Debug)]
+pub enum GltfExportError {
+    MissingInvBindpose(usize),
+    InvalidJoint { vertex: usize, joint: usize },
+    InvalidTarget { animation: usize, target: usize },
+    // JOINTS_0 is written as u16 so only that many joints can be indexed
+    TooManyJoints(usize),
+    // glTF accessors can't be empty
+    EmptyMesh,
+    // A frame has fewer poses than the animation has targets
+    MissingPoses { animation: usize, frame: usize },
+}
+
+impl Display for GltfExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfExportError::MissingInvBindpose(joint) => write!(f, "joint {} is missing its inverse bind pose", joint),
+            GltfExportError::InvalidJoint { vertex, joint } => write!(f, "vertex {} references missing joint {}", vertex, joint),
+            GltfExportError::InvalidTarget { animation, target } => write!(f, "animation {} targets missing joint {}", animation, target),
+            GltfExportError::TooManyJoints(joints) => write!(f, "skeleton has {} joints but at most {} can be exported", joints, u16::MAX as usize + 1),
+            GltfExportError::EmptyMesh => write!(f, "mesh has no vertices"),
+            GltfExportError::MissingPoses { animation, frame } => write!(f, "frame {} of animation {} has fewer poses than targets", frame, animation),
+        }
+    }
+}
+
+impl Error for GltfExportError {}
+
+// Builds up the json and the binary chunk of a glb file
+struct GlbBuilder {
+    gltf: Gltf,
+    buffer: Vec<u8>,
+}
+
+impl GlbBuilder {
+    fn new() -> GlbBuilder {
+        let mut gltf = Gltf::default();
+        gltf.asset.generator = Some("animator_lib".to_string());
+
+        GlbBuilder {
+            gltf,
+            buffer: vec![],
+        }
+    }
+
+    fn push_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        while self.buffer.len() % 4 != 0 {
+            self.buffer.push(0);
+        }
+
+        self.gltf.buffer_views.push(BufferView {
+            buffer: 0,
+            byte_offset: self.buffer.len(),
+            byte_length: bytes.len(),
+            byte_stride: None,
+            target,
+        });
+        self.buffer.extend_from_slice(bytes);
+
+        self.gltf.buffer_views.len() - 1
+    }
+
+    fn push_accessor(&mut self, view: usize, component_type: u32, count: usize, accessor_type: &str, bounds: Option<(Vec<f32>, Vec<f32>)>) -> usize {
+        let (min, max) = match bounds {
+            Some((min, max)) => (Some(min), Some(max)),
+            None => (None, None),
+        };
+
+        self.gltf.accessors.push(Accessor {
+            buffer_view: Some(view),
+            byte_offset: 0,
+            component_type,
+            normalized: false,
+            count,
+            accessor_type: accessor_type.to_string(),
+            min,
+            max,
+        });
+
+        self.gltf.accessors.len() - 1
+    }
+
+    fn push_floats(&mut self, values: &[f32], accessor_type: &str, with_bounds: bool, target: Option<u32>) -> usize {
+        let components = component_count(accessor_type).unwrap();
+        let count = values.len() / components;
+
+        let bounds = match with_bounds && count > 0 {
+            true => {
+                let mut min = values[..components].to_vec();
+                let mut max = min.clone();
+                for element in values.chunks(components) {
+                    for i in 0..components {
+                        min[i] = min[i].min(element[i]);
+                        max[i] = max[i].max(element[i]);
+                    }
+                }
+
+                Some((min, max))
+            }
+            false => None,
+        };
+
+        let mut bytes = Vec::with_capacity(values.len() * 4);
+        for value in values {
+            bytes.extend_from_slice(&u32_bytes(value.to_bits()));
+        }
+
+        let view = self.push_view(&bytes, target);
+        self.push_accessor(view, FLOAT, count, accessor_type, bounds)
+    }
+
+    fn push_indices(&mut self, indices: &[usize]) -> usize {
+        let mut bytes = Vec::with_capacity(indices.len() * 4);
+        for index in indices {
+            bytes.extend_from_slice(&u32_bytes(*index as u32));
+        }
+
+        let view = self.push_view(&bytes, Some(34963));
+        self.push_accessor(view, UNSIGNED_INT, indices.len(), "SCALAR", None)
+    }
+
+    fn push_joints(&mut self, joints: &[u16]) -> usize {
+        let mut bytes = Vec::with_capacity(joints.len() * 2);
+        for joint in joints {
+            bytes.push(*joint as u8);
+            bytes.push((*joint >> 8) as u8);
+        }
+
+        let view = self.push_view(&bytes, Some(34962));
+        self.push_accessor(view, UNSIGNED_SHORT, joints.len() / 4, "VEC4", None)
+    }
+
+    fn into_glb(mut self) -> Result<Vec<u8>, Box<Error>> {
+        while self.buffer.len() % 4 != 0 {
+            self.buffer.push(0);
+        }
+
+        if !self.buffer.is_empty() {
+            self.gltf.buffers.push(Buffer {
+                byte_length: self.buffer.len(),
+                uri: None,
+            });
+        }
+
+        let mut json = serde_json::to_vec(&self.gltf)?;
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+
+        let mut length = 12 + 8 + json.len();
+        if !self.buffer.is_empty() {
+            length += 8 + self.buffer.len();
+        }
+
+        let mut glb = Vec::with_capacity(length);
+        glb.extend_from_slice(&u32_bytes(GLB_MAGIC));
+        glb.extend_from_slice(&u32_bytes(2));
+        glb.extend_from_slice(&u32_bytes(length as u32));
+
+        glb.extend_from_slice(&u32_bytes(json.len() as u32));
+        glb.extend_from_slice(&u32_bytes(GLB_JSON_CHUNK));
+        glb.extend_from_slice(&json);
+
+        if !self.buffer.is_empty() {
+            glb.extend_from_slice(&u32_bytes(self.buffer.len() as u32));
+            glb.extend_from_slice(&u32_bytes(GLB_BIN_CHUNK));
+            glb.extend_from_slice(&self.buffer);
+        }
+
+        Ok(glb)
+    }
+}
+
+fn u32_bytes(value: u32) -> [u8; 4] {
+    [value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]
+}
+
+fn pose_node(name: String, pose: &Pose) -> Node {
+    let r = pose.rotation.coords;
+
+    Node {
+        name: Some(name),
+        translation: Some([pose.translation.x, pose.translation.y, pose.translation.z]),
+        rotation: Some([r.x, r.y, r.z, r.w]),
+        scale: Some([pose.scale.x, pose.scale.y, pose.scale.z]),
+        ..Node::default()
+    }
+}
+
+// Up to 4 of the largest weights normalized to sum to 1
+fn vertex_influences(weights: &[(usize, f32)]) -> [(usize, f32); 4] {
+    let mut sorted = weights.to_vec();
+    sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(::std::cmp::Ordering::Equal));
+    sorted.truncate(4);
+
+    let sum: f32 = sorted.iter().map(|x| x.1).sum();
+    let mut influences = [(0, 0.0); 4];
+    for (i, weight) in sorted.iter().enumerate() {
+        if sum > 0.0 {
+            influences[i] = (weight.0, weight.1 / sum);
+        }
+    }
+
+    influences
+}
+
+fn add_mesh<V: MeshVertex>(builder: &mut GlbBuilder, mesh: &mesh::Mesh<V>, joints: usize) -> Result<usize, GltfExportError> {
+    let vertices = &mesh.vertices;
+    if vertices.is_empty() {
+        return Err(GltfExportError::EmptyMesh);
+    }
+
+    let mut attributes = BTreeMap::new();
+
+    let positions: Vec<f32> = vertices.iter().flat_map(|v| {
+        let p = v.position();
+        vec![p.x, p.y, p.z]
+    }).collect();
+    attributes.insert("POSITION".to_string(), builder.push_floats(&positions, "VEC3", true, Some(34962)));
+
+    if vertices.iter().all(|v| v.normal().is_some()) {
+        let normals: Vec<f32> = vertices.iter().flat_map(|v| {
+            let n = v.normal().unwrap();
+            vec![n.x, n.y, n.z]
+        }).collect();
+        attributes.insert("NORMAL".to_string(), builder.push_floats(&normals, "VEC3", false, Some(34962)));
+    }
+
+    if vertices.iter().all(|v| v.tex_coord().is_some()) {
+        let tex_coords: Vec<f32> = vertices.iter().flat_map(|v| {
+            let t = v.tex_coord().unwrap();
+            vec![t.x, t.y]
+        }).collect();
+        attributes.insert("TEXCOORD_0".to_string(), builder.push_floats(&tex_coords, "VEC2", false, Some(34962)));
+    }
+
+    if vertices.iter().any(|v| !v.joint_weights().is_empty()) {
+        let mut joint_indices = Vec::with_capacity(vertices.len() * 4);
+        let mut weights = Vec::with_capacity(vertices.len() * 4);
+
+        for (i, vertex) in vertices.iter().enumerate() {
+            for (joint, weight) in vertex_influences(&vertex.joint_weights()).iter() {
+                if *joint >= joints {
+                    return Err(GltfExportError::InvalidJoint { vertex: i, joint: *joint });
+                }
+
+                joint_indices.push(*joint as u16);
+                weights.push(*weight);
+            }
+        }
+
+        attributes.insert("JOINTS_0".to_string(), builder.push_joints(&joint_indices));
+        attributes.insert("WEIGHTS_0".to_string(), builder.push_floats(&weights, "VEC4", false, Some(34962)));
+    }
+
+    let mut triangles = vec![];
+    let mut lines = vec![];
+    let mut points = vec![];
+    for shape in &mesh.shapes {
+        match shape {
+            mesh::Shape::Triangle(a, b, c) => triangles.extend(&[*a, *b, *c]),
+            mesh::Shape::Line(a, b) => lines.extend(&[*a, *b]),
+            mesh::Shape::Point(a) => points.push(*a),
+        }
+    }
+
+    let mut primitives = vec![];
+    for (indices, mode) in vec![(triangles, TRIANGLES), (lines, LINES), (points, POINTS)] {
+        if indices.is_empty() {
+            continue;
+        }
+
+        primitives.push(Primitive {
+            attributes: attributes.clone(),
+            indices: Some(builder.push_indices(&indices)),
+            mode,
+        });
+    }
+
+    builder.gltf.meshes.push(Mesh {
+        name: None,
+        primitives,
+    });
+
+    Ok(builder.gltf.meshes.len() - 1)
+}
+
+fn add_animation<A: Animation>(builder: &mut GlbBuilder, index: usize, name: &str, animation: &A, joints: usize) -> Result<(), GltfExportError> {
+    let frames = animation.frames();
+    if frames == 0 {
+        return Ok(());
+    }
+
+    let (first, _) = animation.get_frame(0).unwrap();
+    let bones = first.len();
+    let targets: Vec<usize> = match animation.get_targets() {
+        Targets::Specified(targets) => targets.to_vec(),
+        Targets::InOrder => (0..bones.min(joints)).collect(),
+    };
+
+    if let Some(target) = targets.iter().find(|x| **x >= joints) {
+        return Err(GltfExportError::InvalidTarget { animation: index, target: *target });
+    }
+
+    let input = builder.push_floats(animation.sample_times(), "SCALAR", true, None);
+    let mut gltf_animation = ::gltf_load::document::Animation {
+        name: Some(name.to_string()),
+        channels: vec![],
+        samplers: vec![],
+    };
+
+    for (bone, target) in targets.iter().enumerate() {
+        let mut translations = Vec::with_capacity(frames * 3);
+        let mut rotations = Vec::with_capacity(frames * 4);
+        let mut scales = Vec::with_capacity(frames * 3);
+
+        for frame in 0..frames {
+            let pose = match animation.get_frame(frame) {
+                Some((poses, _)) if bone < poses.len() => &poses[bone],
+                _ => return Err(GltfExportError::MissingPoses { animation: index, frame }),
+            };
+            let r = pose.rotation.coords;
+
+            translations.extend(&[pose.translation.x, pose.translation.y, pose.translation.z]);
+            rotations.extend(&[r.x, r.y, r.z, r.w]);
+            scales.extend(&[pose.scale.x, pose.scale.y, pose.scale.z]);
+        }
+
+        for (values, path, accessor_type) in vec![(translations, "translation", "VEC3"), (rotations, "rotation", "VEC4"), (scales, "scale", "VEC3")] {
+            let output = builder.push_floats(&values, accessor_type, false, None);
+            gltf_animation.samplers.push(Sampler {
+                input,
+                output,
+                interpolation: "LINEAR".to_string(),
+            });
+            gltf_animation.channels.push(Channel {
+                sampler: gltf_animation.samplers.len() - 1,
+                target: ChannelTarget {
+                    node: Some(*target),
+                    path: path.to_string(),
+                },
+            });
+        }
+    }
+
+    builder.gltf.animations.push(gltf_animation);
+    Ok(())
+}
+
+// Writes the skeleton, the skinned mesh and the animations as a glb. The joints become nodes
+// 0..bone_count in skeleton order and the skin uses the same order, so the file loads back
+// with the same joint indices through gltf_load
+pub fn export_glb<V: MeshVertex, A: Animation>(skeleton: &Skeleton, mesh: &mesh::Mesh<V>, animations: &[(&str, &A)]) -> Result<Vec<u8>, Box<Error>> {
+    let mut builder = GlbBuilder::new();
+    let joints = skeleton.bone_count();
+    if joints > u16::MAX as usize + 1 {
+        return Err(GltfExportError::TooManyJoints(joints).into());
+    }
+
+    let mut inv_bind_matrices = Vec::with_capacity(joints * 16);
+    for (i, inv_bind_pose) in skeleton.inv_bind_pose_ref().iter().enumerate() {
+        let matrix = inv_bind_pose.ok_or(GltfExportError::MissingInvBindpose(i))?.matrix();
+        inv_bind_matrices.extend_from_slice(matrix.as_slice());
+    }
+
+    for (i, pose) in skeleton.pose_ref().iter().enumerate() {
+        let name = match skeleton.joint_name(i) {
+            Some(name) => name.to_string(),
+            None => format!("joint_{}", i),
+        };
+        builder.gltf.nodes.push(pose_node(name, pose));
+    }
+
+    let mut roots = vec![];
+    for (i, parent) in skeleton.tree_ref().iter().enumerate() {
+        match parent {
+            Some(parent) => builder.gltf.nodes[*parent].children.push(i),
+            None => roots.push(i),
+        }
+    }
+
+    let gltf_mesh = add_mesh(&mut builder, mesh, joints)?;
+    let inverse_bind_matrices = builder.push_floats(&inv_bind_matrices, "MAT4", false, None);
+
+    builder.gltf.skins.push(Skin {
+        name: None,
+        inverse_bind_matrices: Some(inverse_bind_matrices),
+        skeleton: roots.first().cloned(),
+        joints: (0..joints).collect(),
+    });
+
+    builder.gltf.nodes.push(Node {
+        name: Some("mesh".to_string()),
+        mesh: Some(gltf_mesh),
+        skin: Some(0),
+        ..Node::default()
+    });
+    roots.push(builder.gltf.nodes.len() - 1);
+
+    builder.gltf.scenes.push(Scene {
+        name: None,
+        nodes: roots,
+    });
+    builder.gltf.scene = Some(0);
+
+    for (i, (name, animation)) in animations.iter().enumerate() {
+        add_animation(&mut builder, i, name, *animation, joints)?;
+    }
+
+    builder.into_glb()
+}
+
+pub fn save_glb<V: MeshVertex, A: Animation>(path: impl AsRef<Path>, skeleton: &Skeleton, mesh: &mesh::Mesh<V>, animations: &[(&str, &A)]) -> Result<(), Box<Error>> {
+    let glb = export_glb(skeleton, mesh, animations)?;
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+
+    writer.write_all(&glb)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gltf_load::{skeleton::load_skeleton, mesh::load_mesh, animation::load_animation};
+    use collada_load::mesh::VertexFromParts;
+    use collada_parser::collada::skin::JointWeight;
+    use collada_parser::math::{Vector2, Vector3};
+    use math::vec3_from_vector3;
+    use glm::{self, Vec3};
+    use animation;
+
+    #[derive(Copy, Clone, Debug)]
+    struct TestVertex {
+        position: Vec3,
+        weights: [(usize, f32); 2],
+    }
+
+    impl MeshVertex for TestVertex {
+        fn position(&self) -> Vec3 {
+            self.position
+        }
+
+        fn set_position(&mut self, position: Vec3) {
+            self.position = position;
+        }
+
+        fn joint_weights(&self) -> Vec<(usize, f32)> {
+            self.weights.iter().cloned().filter(|x| x.1 > 0.0).collect()
+        }
+    }
+
+    impl VertexFromParts for TestVertex {
+        fn from_parts(vertex: Vector3, _: Option<Vector2>, _: Option<Vector3>, weights: Option<&[JointWeight]>) -> Option<TestVertex> {
+            let mut vertex = TestVertex { position: vec3_from_vector3(&vertex), weights: [(0, 0.0); 2] };
+            for (i, weight) in weights?.iter().take(2).enumerate() {
+                vertex.weights[i] = (weight.joint, weight.weight);
+            }
+
+            Some(vertex)
+        }
+    }
+
+    #[test]
+    fn glb_round_trip() {
+        let mut skeleton = Skeleton::from_tree_pose(vec![None, Some(0)], vec![Pose::only_trans(0.0, 1.0, 0.0), Pose::only_trans(1.0, 0.0, 0.0)]);
+        skeleton.build_inv_bind_poses();
+        skeleton.set_joint_name(0, "hips");
+
+        let mesh = mesh::Mesh::new(vec![
+            TestVertex { position: Vec3::new(0.0, 1.0, 0.0), weights: [(0, 1.0), (0, 0.0)] },
+            TestVertex { position: Vec3::new(1.0, 1.0, 0.0), weights: [(1, 0.75), (0, 0.25)] },
+            TestVertex { position: Vec3::new(1.0, 2.0, 0.0), weights: [(1, 1.0), (0, 0.0)] },
+        ], vec![mesh::Shape::Triangle(0, 1, 2)]);
+
+        let mut clip = animation::Animation::new(1, Some(vec![1]));
+        clip.add_frame(&[Pose::only_trans(1.0, 0.0, 0.0)], 0.0);
+        clip.add_frame(&[Pose::only_trans(2.0, 0.0, 0.0)], 0.5);
+
+        let glb = export_glb(&skeleton, &mesh, &[("walk", &clip)]).unwrap();
+        let document = Document::from_slice(&glb, None).unwrap();
+
+        let loaded = load_skeleton(&document, 0).unwrap();
+        assert!(loaded.tree_ref() == skeleton.tree_ref());
+        assert!(document.gltf.nodes[0].name == Some("hips".to_string()));
+        assert!(document.gltf.nodes[1].name == Some("joint_1".to_string()));
+        for (a, b) in loaded.inv_bind_pose_ref().iter().zip(skeleton.inv_bind_pose_ref()) {
+            assert!(glm::distance(&a.unwrap().translation, &b.unwrap().translation) < 1e-5);
+        }
+
+        let loaded_mesh: mesh::Mesh<TestVertex> = load_mesh(&document, 0).unwrap();
+        assert!(loaded_mesh.vertices.len() == 3);
+        assert!(loaded_mesh.vertices[1].joint_weights() == vec![(1, 0.75), (0, 0.25)]);
+
+        let loaded_clip = load_animation(&document, 0, 0).unwrap();
+        assert!(loaded_clip.targets == Some(vec![1]));
+        assert!(loaded_clip.times == clip.times);
+        let (poses, _) = loaded_clip.get_frame_and_time(1);
+        assert!(glm::distance(&poses[0].translation, &Vec3::new(2.0, 0.0, 0.0)) < 1e-6);
+        assert!(document.gltf.animations[0].name == Some("walk".to_string()));
+    }
+
+    #[test]
+    fn too_many_joints() {
+        let joints = u16::MAX as usize + 2;
+        let tree = (0..joints).map(|i| if i == 0 { None } else { Some(0) }).collect();
+        let skeleton = Skeleton::from_tree_pose(tree, vec![Pose::pose_identity(); joints]);
+        let mesh: mesh::Mesh<TestVertex> = mesh::Mesh::new(vec![], vec![]);
+
+        let error = export_glb::<_, animation::Animation>(&skeleton, &mesh, &[]).unwrap_err();
+        assert!(error.to_string().contains(&joints.to_string()));
+    }
+
+    #[test]
+    fn invalid_exports() {
+        let mut skeleton = Skeleton::from_tree_pose(vec![None, Some(0)], vec![Pose::pose_identity(); 2]);
+        skeleton.build_inv_bind_poses();
+        let none: &[(&str, &animation::Animation)] = &[];
+
+        let empty: mesh::Mesh<TestVertex> = mesh::Mesh::new(vec![], vec![]);
+        match export_glb(&skeleton, &empty, none).unwrap_err().downcast_ref::<GltfExportError>() {
+            Some(GltfExportError::EmptyMesh) => {}
+            _ => panic!("expected an empty mesh error"),
+        }
+
+        // Two targets but only one pose per frame
+        let mesh = mesh::Mesh::new(vec![TestVertex { position: Vec3::zeros(), weights: [(0, 1.0), (0, 0.0)] }], vec![mesh::Shape::Point(0)]);
+        let clip = animation::Animation::from_poses_and_times(1, &[Pose::pose_identity()], &[0.0], Some(vec![0, 1]));
+        match export_glb(&skeleton, &mesh, &[("short", &clip)]).unwrap_err().downcast_ref::<GltfExportError>() {
+            Some(GltfExportError::MissingPoses { animation: 0, frame: 0 }) => {}
+            _ => panic!("expected a missing poses error"),
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use super::GltfLoadError;
+use super::document::*;
+use super::skeleton::{joint_parent, node_pose};
+use animation;
+use pose::*;
+use glm::{self, Quat, Vec3};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    CubicSpline,
+}
+
+impl Interpolation {
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        match name {
+            "STEP" => Some(Interpolation::Step),
+            "LINEAR" => Some(Interpolation::Linear),
+            "CUBICSPLINE" => Some(Interpolation::CubicSpline),
+            _ => None,
+        }
+    }
+}
+
+// The keys of a single sampler
+#[derive(Clone, Debug)]
+pub struct Curve {
+    pub times: Vec<f32>,
+    // For cubic splines every key stores the in tangent, the value and then the out tangent
+    pub values: Vec<f32>,
+    pub components: usize,
+    pub interpolation: Interpolation,
+}
+
+impl Curve {
+    fn key(&self, key: usize, part: usize) -> &[f32] {
+        let stride = match self.interpolation {
+            Interpolation::CubicSpline => self.components * 3,
+            _ => self.components,
+        };
+        let offset = match self.interpolation {
+            Interpolation::CubicSpline => part * self.components,
+            _ => 0,
+        };
+
+        let start = key * stride + offset;
+        &self.values[start..start + self.components]
+    }
+
+    // Samples the curve, times outside of the keys are clamped to the first or last key.
+    // Rotations are treated as quaternions and normalized
+    pub fn sample(&self, time: f32, rotation: bool) -> Vec<f32> {
+        let last = self.times.len() - 1;
+        if time <= self.times[0] {
+            return self.key(0, 1).to_vec();
+        }
+        if time >= self.times[last] {
+            return self.key(last, 1).to_vec();
+        }
+
+        let next = match self.times.binary_search_by(|x| x.partial_cmp(&time).unwrap()) {
+            Ok(index) => return self.key(index, 1).to_vec(),
+            Err(index) => index,
+        };
+        let current = next - 1;
+
+        let dt = self.times[next] - self.times[current];
+        let s = (time - self.times[current]) / dt;
+
+        let mut value: Vec<f32> = match self.interpolation {
+            Interpolation::Step => self.key(current, 1).to_vec(),
+            Interpolation::Linear if rotation => {
+                let a = self.key(current, 1);
+                let b = self.key(next, 1);
+                let a = Quat::new(a[3], a[0], a[1], a[2]);
+                let b = Quat::new(b[3], b[0], b[1], b[2]);
+                let mixed = pose_interp(&Pose::only_rot(a), &Pose::only_rot(b), s).rotation;
+
+                return vec![mixed.coords.x, mixed.coords.y, mixed.coords.z, mixed.coords.w];
+            }
+            Interpolation::Linear => {
+                let a = self.key(current, 1);
+                let b = self.key(next, 1);
+                a.iter().zip(b).map(|(a, b)| a * (1.0 - s) + b * s).collect()
+            }
+            Interpolation::CubicSpline => {
+                let v0 = self.key(current, 1);
+                let b0 = self.key(current, 2);
+                let a1 = self.key(next, 0);
+                let v1 = self.key(next, 1);
+
+                let s2 = s * s;
+                let s3 = s2 * s;
+                let h00 = 2.0 * s3 - 3.0 * s2 + 1.0;
+                let h10 = s3 - 2.0 * s2 + s;
+                let h01 = -2.0 * s3 + 3.0 * s2;
+                let h11 = s3 - s2;
+
+                (0..self.components).map(|i| h00 * v0[i] + h10 * dt * b0[i] + h01 * v1[i] + h11 * dt * a1[i]).collect()
+            }
+        };
+
+        if rotation {
+            let length = value.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if length > 0.0 {
+                for x in value.iter_mut() {
+                    *x /= length;
+                }
+            }
+        }
+
+        value
+    }
+}
+
+pub fn load_curve(document: &Document, animation: usize, sampler: usize) -> Result<Curve, GltfLoadError> {
+    let invalid = GltfLoadError::InvalidSampler { animation, sampler };
+    let gltf_sampler = document.gltf.animations[animation].samplers.get(sampler).ok_or(invalid.clone())?;
+    let interpolation = Interpolation::from_name(&gltf_sampler.interpolation).ok_or(invalid.clone())?;
+
+    let times = document.read_floats(gltf_sampler.input)?;
+    let values = document.read_floats(gltf_sampler.output)?;
+    let keys = match interpolation {
+        Interpolation::CubicSpline => times.len() * 3,
+        _ => times.len(),
+    };
+
+    // Non-finite times can't be ordered against the other keys
+    if times.is_empty() || times.iter().any(|x| !x.is_finite()) || values.len() % keys != 0 {
+        return Err(invalid);
+    }
+
+    Ok(Curve {
+        components: values.len() / keys,
+        times,
+        values,
+        interpolation,
+    })
+}
+
+#[derive(Default)]
+struct JointCurves {
+    translation: Option<Curve>,
+    rotation: Option<Curve>,
+    scale: Option<Curve>,
+}
+
+// Samples every channel that targets a joint of the skin onto the union of all of the key times.
+// The bones are in the order used by load_skeleton. Morph target weights are ignored
+pub fn load_animation(document: &Document, animation: usize, skin: usize) -> Result<animation::Animation, GltfLoadError> {
+    let gltf_animation = document.gltf.animations.get(animation).ok_or(GltfLoadError::MissingAnimation(animation))?;
+    let gltf_skin = document.gltf.skins.get(skin).ok_or(GltfLoadError::MissingSkin(skin))?;
+    let joints = &gltf_skin.joints;
+
+    let mut curves: Vec<JointCurves> = joints.iter().map(|_| JointCurves::default()).collect();
+    let mut times: Vec<f32> = vec![];
+
+    for channel in &gltf_animation.channels {
+        let joint = match channel.target.node.and_then(|node| joints.iter().position(|x| *x == node)) {
+            Some(joint) => joint,
+            None => continue,
+        };
+
+        let curve = load_curve(document, animation, channel.sampler)?;
+        let expected = match channel.target.path.as_str() {
+            "translation" | "scale" => 3,
+            "rotation" => 4,
+            _ => continue,
+        };
+
+        if curve.components != expected {
+            return Err(GltfLoadError::InvalidSampler { animation, sampler: channel.sampler });
+        }
+
+        times.extend(&curve.times);
+        match channel.target.path.as_str() {
+            "translation" => curves[joint].translation = Some(curve),
+            "rotation" => curves[joint].rotation = Some(curve),
+            _ => curves[joint].scale = Some(curve),
+        }
+    }
+
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    times.dedup_by(|a, b| (*a - *b).abs() <= 1e-6);
+
+    let animated: Vec<usize> = (0..joints.len()).filter(|i| {
+        let curves = &curves[*i];
+        curves.translation.is_some() || curves.rotation.is_some() || curves.scale.is_some()
+    }).collect();
+
+    let targets = match animated.len() == joints.len() {
+        true => None,
+        false => Some(animated.clone()),
+    };
+
+    let parents = document.node_parents();
+    let rest = animated.iter().map(|joint| {
+        let node = joints[*joint];
+        let (_, prefix) = joint_parent(document, &parents, joints, node)?;
+        Ok((prefix, node_pose(&document.gltf.nodes[node])))
+    }).collect::<Result<Vec<(Pose, Pose)>, GltfLoadError>>()?;
+
+    let mut clip = animation::Animation::with_capacity(animated.len(), times.len(), targets);
+    let start = times.first().cloned().unwrap_or(0.0);
+    let mut poses = Vec::with_capacity(animated.len());
+
+    for time in &times {
+        poses.clear();
+
+        for (joint, (prefix, rest)) in animated.iter().zip(&rest) {
+            let curves = &curves[*joint];
+            let mut pose = *rest;
+
+            if let Some(curve) = &curves.translation {
+                let t = curve.sample(*time, false);
+                pose.translation = Vec3::new(t[0], t[1], t[2]);
+            }
+            if let Some(curve) = &curves.rotation {
+                let r = curve.sample(*time, true);
+                pose.rotation = glm::quat_normalize(&Quat::new(r[3], r[0], r[1], r[2]));
+            }
+            if let Some(curve) = &curves.scale {
+                let s = curve.sample(*time, false);
+                pose.scale = Vec3::new(s[0], s[1], s[2]);
+            }
+
+            poses.push(*prefix * pose);
+        }
+
+        clip.add_frame(&poses, *time - start);
+    }
+
+    Ok(clip)
+}
+
+// Loads every animation in the document for the skin, named by the animation name or index
+pub fn load_animations(document: &Document, skin: usize) -> Result<Vec<(String, animation::Animation)>, GltfLoadError> {
+    (0..document.gltf.animations.len()).map(|i| {
+        let name = document.gltf.animations[i].name.clone().unwrap_or_else(|| format!("animation_{}", i));
+        load_animation(document, i, skin).map(|x| (name, x))
+    }).collect()
+}
@@ -0,0 +1,501 @@
+use super::GltfLoadError;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use serde_json;
+
+pub const GLB_MAGIC: u32 = 0x4654_6C67;
+pub const GLB_JSON_CHUNK: u32 = 0x4E4F_534A;
+pub const GLB_BIN_CHUNK: u32 = 0x004E_4942;
+
+pub const FLOAT: u32 = 5126;
+pub const UNSIGNED_INT: u32 = 5125;
+pub const UNSIGNED_SHORT: u32 = 5123;
+pub const SHORT: u32 = 5122;
+pub const UNSIGNED_BYTE: u32 = 5121;
+pub const BYTE: u32 = 5120;
+
+pub const POINTS: u32 = 0;
+pub const LINES: u32 = 1;
+pub const LINE_LOOP: u32 = 2;
+pub const LINE_STRIP: u32 = 3;
+pub const TRIANGLES: u32 = 4;
+pub const TRIANGLE_STRIP: u32 = 5;
+pub const TRIANGLE_FAN: u32 = 6;
+
+fn default_mode() -> u32 {
+    TRIANGLES
+}
+
+fn default_interpolation() -> String {
+    "LINEAR".to_string()
+}
+
+// The parts of the glTF 2.0 json that are used for characters, everything else is ignored
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gltf {
+    pub asset: Asset,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scene: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scenes: Vec<Scene>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<Node>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub meshes: Vec<Mesh>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skins: Vec<Skin>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub animations: Vec<Animation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub accessors: Vec<Accessor>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buffer_views: Vec<BufferView>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub buffers: Vec<Buffer>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Asset {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+}
+
+impl Default for Asset {
+    fn default() -> Asset {
+        Asset {
+            version: "2.0".to_string(),
+            generator: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scene {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Node {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skin: Option<usize>,
+    // Column major
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matrix: Option<[f32; 16]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<[f32; 3]>,
+    // In the form [x, y, z, w]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<[f32; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<[f32; 3]>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Mesh {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub primitives: Vec<Primitive>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Primitive {
+    pub attributes: BTreeMap<String, usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indices: Option<usize>,
+    #[serde(default = "default_mode")]
+    pub mode: u32,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Skin {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inverse_bind_matrices: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skeleton: Option<usize>,
+    pub joints: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Animation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub channels: Vec<Channel>,
+    pub samplers: Vec<Sampler>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Channel {
+    pub sampler: usize,
+    pub target: ChannelTarget,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChannelTarget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<usize>,
+    // One of "translation", "rotation", "scale" or "weights"
+    pub path: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Sampler {
+    pub input: usize,
+    pub output: usize,
+    // One of "STEP", "LINEAR" or "CUBICSPLINE"
+    #[serde(default = "default_interpolation")]
+    pub interpolation: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Accessor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buffer_view: Option<usize>,
+    #[serde(default)]
+    pub byte_offset: usize,
+    pub component_type: u32,
+    #[serde(default)]
+    pub normalized: bool,
+    pub count: usize,
+    // One of "SCALAR", "VEC2", "VEC3", "VEC4", "MAT2", "MAT3" or "MAT4"
+    #[serde(rename = "type")]
+    pub accessor_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Vec<f32>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BufferView {
+    pub buffer: usize,
+    #[serde(default)]
+    pub byte_offset: usize,
+    pub byte_length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_stride: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Buffer {
+    pub byte_length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+}
+
+pub fn component_count(accessor_type: &str) -> Option<usize> {
+    match accessor_type {
+        "SCALAR" => Some(1),
+        "VEC2" => Some(2),
+        "VEC3" => Some(3),
+        "VEC4" => Some(4),
+        "MAT2" => Some(4),
+        "MAT3" => Some(9),
+        "MAT4" => Some(16),
+        _ => None,
+    }
+}
+
+pub fn component_size(component_type: u32) -> Option<usize> {
+    match component_type {
+        FLOAT | UNSIGNED_INT => Some(4),
+        UNSIGNED_SHORT | SHORT => Some(2),
+        UNSIGNED_BYTE | BYTE => Some(1),
+        _ => None,
+    }
+}
+
+// A parsed glTF file with all of its buffers loaded
+#[derive(Clone, Debug)]
+pub struct Document {
+    pub gltf: Gltf,
+    pub buffers: Vec<Vec<u8>>,
+}
+
+impl Document {
+    // Loads a .gltf or .glb file, external buffers are loaded relative to the file
+    pub fn load(path: impl AsRef<Path>) -> Result<Document, Box<Error>> {
+        let path = path.as_ref();
+        let mut bytes = vec![];
+        File::open(path)?.read_to_end(&mut bytes)?;
+
+        Document::from_slice(&bytes, path.parent())
+    }
+
+    pub fn from_slice(bytes: &[u8], base: Option<&Path>) -> Result<Document, Box<Error>> {
+        let (gltf, bin): (Gltf, Option<Vec<u8>>) = match bytes.len() >= 12 && read_u32(bytes, 0) == GLB_MAGIC {
+            true => {
+                let (json, bin) = split_glb(bytes)?;
+                (serde_json::from_slice(json)?, bin.map(|x| x.to_vec()))
+            }
+            false => (serde_json::from_slice(bytes)?, None),
+        };
+
+        let mut bin = bin;
+        let mut buffers = Vec::with_capacity(gltf.buffers.len());
+        for (i, buffer) in gltf.buffers.iter().enumerate() {
+            let data = match &buffer.uri {
+                Some(uri) if uri.starts_with("data:") => decode_data_uri(uri)?,
+                Some(uri) => {
+                    let path = match base {
+                        Some(base) => base.join(uri),
+                        None => Path::new(uri).to_path_buf(),
+                    };
+
+                    let mut data = vec![];
+                    File::open(path)?.read_to_end(&mut data)?;
+                    data
+                }
+                None => bin.take().ok_or(GltfLoadError::MissingBuffer(i))?,
+            };
+
+            if data.len() < buffer.byte_length {
+                return Err(Box::new(GltfLoadError::MissingBuffer(i)));
+            }
+
+            buffers.push(data);
+        }
+
+        Ok(Document {
+            gltf,
+            buffers,
+        })
+    }
+
+    fn read_raw<F: FnMut(f64)>(&self, index: usize, mut push: F) -> Result<&Accessor, GltfLoadError> {
+        let accessor = self.gltf.accessors.get(index).ok_or(GltfLoadError::InvalidAccessor(index))?;
+        let components = component_count(&accessor.accessor_type).ok_or(GltfLoadError::InvalidAccessor(index))?;
+        let size = component_size(accessor.component_type).ok_or(GltfLoadError::InvalidAccessor(index))?;
+
+        let view = match accessor.buffer_view {
+            Some(view) => self.gltf.buffer_views.get(view).ok_or(GltfLoadError::InvalidAccessor(index))?,
+            None => {
+                // Accessors without a view are all zeros
+                let values = accessor.count.checked_mul(components).ok_or(GltfLoadError::InvalidAccessor(index))?;
+                for _ in 0..values {
+                    push(0.0);
+                }
+
+                return Ok(accessor);
+            }
+        };
+
+        let buffer = self.buffers.get(view.buffer).ok_or(GltfLoadError::MissingBuffer(view.buffer))?;
+        let element_size = components * size;
+        let stride = view.byte_stride.unwrap_or(element_size);
+        let start = view.byte_offset.checked_add(accessor.byte_offset).ok_or(GltfLoadError::InvalidAccessor(index))?;
+
+        // All the arithmetic below stays under end once it is known to fit the buffer
+        if accessor.count > 0 {
+            let end = stride.checked_mul(accessor.count - 1)
+                .and_then(|x| x.checked_add(start))
+                .and_then(|x| x.checked_add(element_size))
+                .ok_or(GltfLoadError::InvalidAccessor(index))?;
+            let view_end = view.byte_offset.checked_add(view.byte_length).ok_or(GltfLoadError::InvalidAccessor(index))?;
+            if end > buffer.len() || end > view_end {
+                return Err(GltfLoadError::InvalidAccessor(index));
+            }
+        }
+
+        for element in 0..accessor.count {
+            let offset = start + element * stride;
+            for component in 0..components {
+                let at = offset + component * size;
+                let value = match accessor.component_type {
+                    FLOAT => f32::from_bits(read_u32(buffer, at)) as f64,
+                    UNSIGNED_INT => read_u32(buffer, at) as f64,
+                    UNSIGNED_SHORT => read_u16(buffer, at) as f64,
+                    SHORT => read_u16(buffer, at) as i16 as f64,
+                    UNSIGNED_BYTE => buffer[at] as f64,
+                    _ => buffer[at] as i8 as f64,
+                };
+
+                push(value);
+            }
+        }
+
+        Ok(accessor)
+    }
+
+    // Reads an accessor as floats, normalized integers are mapped to [0, 1] or [-1, 1]
+    pub fn read_floats(&self, index: usize) -> Result<Vec<f32>, GltfLoadError> {
+        let mut values = vec![];
+        let accessor = self.read_raw(index, |x| values.push(x))?;
+
+        if accessor.normalized {
+            let (scale, min) = match accessor.component_type {
+                UNSIGNED_BYTE => (1.0 / 255.0, 0.0),
+                BYTE => (1.0 / 127.0, -1.0),
+                UNSIGNED_SHORT => (1.0 / 65535.0, 0.0),
+                SHORT => (1.0 / 32767.0, -1.0),
+                _ => (1.0, ::std::f64::MIN),
+            };
+
+            for value in values.iter_mut() {
+                *value = (*value * scale).max(min);
+            }
+        }
+
+        Ok(values.into_iter().map(|x| x as f32).collect())
+    }
+
+    // Reads an accessor of integers such as indices or joints
+    pub fn read_indices(&self, index: usize) -> Result<Vec<usize>, GltfLoadError> {
+        let mut values = vec![];
+        let accessor = self.read_raw(index, |x| values.push(x))?;
+        if accessor.component_type == FLOAT {
+            return Err(GltfLoadError::InvalidAccessor(index));
+        }
+
+        Ok(values.into_iter().map(|x| x as usize).collect())
+    }
+
+    pub fn accessor_components(&self, index: usize) -> Result<usize, GltfLoadError> {
+        self.gltf.accessors.get(index)
+            .and_then(|x| component_count(&x.accessor_type))
+            .ok_or(GltfLoadError::InvalidAccessor(index))
+    }
+
+    // Maps every node to its parent
+    pub fn node_parents(&self) -> Vec<Option<usize>> {
+        let mut parents = vec![None; self.gltf.nodes.len()];
+        for (i, node) in self.gltf.nodes.iter().enumerate() {
+            for child in &node.children {
+                if *child < parents.len() {
+                    parents[*child] = Some(i);
+                }
+            }
+        }
+
+        parents
+    }
+}
+
+pub fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    bytes[at] as u32 | (bytes[at + 1] as u32) << 8 | (bytes[at + 2] as u32) << 16 | (bytes[at + 3] as u32) << 24
+}
+
+pub fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    bytes[at] as u16 | (bytes[at + 1] as u16) << 8
+}
+
+// Returns the json chunk and the binary chunk if there is one
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfLoadError> {
+    let length = read_u32(bytes, 8) as usize;
+    if read_u32(bytes, 4) != 2 || length > bytes.len() {
+        return Err(GltfLoadError::InvalidGlb);
+    }
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+
+    while offset + 8 <= length {
+        let chunk_length = read_u32(bytes, offset) as usize;
+        let chunk_type = read_u32(bytes, offset + 4);
+        let start = offset + 8;
+        let end = start + chunk_length;
+        if end > length {
+            return Err(GltfLoadError::InvalidGlb);
+        }
+
+        match chunk_type {
+            GLB_JSON_CHUNK if json.is_none() => json = Some(&bytes[start..end]),
+            GLB_BIN_CHUNK if bin.is_none() => bin = Some(&bytes[start..end]),
+            _ => {}
+        }
+
+        offset = end;
+    }
+
+    Ok((json.ok_or(GltfLoadError::InvalidGlb)?, bin))
+}
+
+fn decode_data_uri(uri: &str) -> Result<Vec<u8>, GltfLoadError> {
+    let data = match uri.find(";base64,") {
+        Some(index) => &uri[index + 8..],
+        None => return Err(GltfLoadError::UnsupportedUri(uri.to_string())),
+    };
+
+    decode_base64(data).ok_or(GltfLoadError::UnsupportedUri(uri.to_string()))
+}
+
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    let mut accumulator: u32 = 0;
+    let mut bits = 0;
+
+    for c in data.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return None,
+        };
+
+        accumulator = accumulator << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((accumulator >> bits) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+pub fn encode_base64(data: &[u8]) -> String {
+    const TABLE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        encoded.push(TABLE[(n >> 18) as usize & 63] as char);
+        encoded.push(TABLE[(n >> 12) as usize & 63] as char);
+        encoded.push(match chunk.len() > 1 { true => TABLE[(n >> 6) as usize & 63] as char, false => '=' });
+        encoded.push(match chunk.len() > 2 { true => TABLE[n as usize & 63] as char, false => '=' });
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trip() {
+        let data = b"animation data!";
+        for length in 0..data.len() {
+            let encoded = encode_base64(&data[..length]);
+            assert!(decode_base64(&encoded).unwrap() == data[..length].to_vec());
+        }
+    }
+}
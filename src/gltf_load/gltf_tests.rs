@@ -0,0 +1,211 @@
+use super::document::*;
+use super::skeleton::load_skeleton;
+use super::animation::{load_animation, Interpolation, Curve};
+use super::mesh::load_mesh;
+use super::GltfLoadError;
+use collada_load::mesh::VertexFromParts;
+use collada_parser::collada::skin::JointWeight;
+use collada_parser::math::{Vector2, Vector3};
+use math::vec3_from_vector3;
+use glm::{self, Vec3};
+
+fn float_bytes(values: &[f32]) -> Vec<u8> {
+    let mut bytes = vec![];
+    for value in values {
+        let bits = value.to_bits();
+        bytes.extend(&[bits as u8, (bits >> 8) as u8, (bits >> 16) as u8, (bits >> 24) as u8]);
+    }
+
+    bytes
+}
+
+// Two joints where the child rotates 90 degrees around z over one second
+fn test_document() -> Document {
+    let half = ::std::f32::consts::FRAC_1_SQRT_2;
+    let buffer = float_bytes(&[0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, half, half]);
+    let json = format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "nodes": [
+            {{ "name": "root", "translation": [0.0, 1.0, 0.0], "children": [1] }},
+            {{ "name": "child", "translation": [0.0, 1.0, 0.0] }}
+        ],
+        "skins": [{{ "joints": [0, 1] }}],
+        "animations": [{{
+            "channels": [{{ "sampler": 0, "target": {{ "node": 1, "path": "rotation" }} }}],
+            "samplers": [{{ "input": 0, "output": 1 }}]
+        }}],
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR" }},
+            {{ "bufferView": 0, "byteOffset": 8, "componentType": 5126, "count": 2, "type": "VEC4" }}
+        ],
+        "bufferViews": [{{ "buffer": 0, "byteLength": 40 }}],
+        "buffers": [{{ "byteLength": 40, "uri": "data:application/octet-stream;base64,{}" }}]
+    }}"#, encode_base64(&buffer));
+
+    Document::from_slice(json.as_bytes(), None).unwrap()
+}
+
+#[test]
+fn gltf_skeleton() {
+    let document = test_document();
+    let mut skeleton = load_skeleton(&document, 0).unwrap();
+
+    assert!(skeleton.tree_ref() == &[None, Some(0)]);
+    skeleton.build_world_poses();
+    let child = skeleton.joint_world_pose(1).unwrap();
+    assert!(glm::distance(&child.translation, &Vec3::new(0.0, 2.0, 0.0)) < 1e-6);
+}
+
+#[test]
+fn gltf_cyclic_nodes() {
+    let load = |nodes: &str| {
+        let json = format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "nodes": [{}],
+            "skins": [{{ "joints": [0, 1] }}]
+        }}"#, nodes);
+        load_skeleton(&Document::from_slice(json.as_bytes(), None).unwrap(), 0)
+    };
+
+    // The joints are each other's parent
+    match load(r#"{ "children": [1] }, { "children": [0] }"#) {
+        Err(GltfLoadError::CyclicNode(0)) => {}
+        _ => panic!("expected a cyclic node error"),
+    }
+
+    // Joint 0 hangs below two other nodes that are each other's parent
+    match load(r#"{ }, { }, { "children": [0, 3] }, { "children": [2] }"#) {
+        Err(GltfLoadError::CyclicNode(0)) => {}
+        _ => panic!("expected a cyclic node error"),
+    }
+}
+
+#[test]
+fn gltf_animation() {
+    let document = test_document();
+    let animation = load_animation(&document, 0, 0).unwrap();
+
+    assert!(animation.targets == Some(vec![1]));
+    assert!(animation.keyframes == 2);
+
+    let (poses, time) = animation.get_frame_and_time(1);
+    let rotated = glm::quat_rotate_vec3(&poses[0].rotation, &Vec3::new(1.0, 0.0, 0.0));
+    assert!(time == 1.0);
+    assert!(glm::distance(&rotated, &Vec3::new(0.0, 1.0, 0.0)) < 1e-5);
+    assert!(glm::distance(&poses[0].translation, &Vec3::new(0.0, 1.0, 0.0)) < 1e-6);
+}
+
+#[test]
+fn cubic_spline_hits_keys() {
+    let curve = Curve {
+        times: vec![0.0, 1.0],
+        // in tangent, value, out tangent for each key
+        values: vec![0.0, 1.0, 2.0, 0.0, 3.0, 0.0],
+        components: 1,
+        interpolation: Interpolation::CubicSpline,
+    };
+
+    assert!(curve.sample(0.0, false) == vec![1.0]);
+    assert!(curve.sample(1.0, false) == vec![3.0]);
+    assert!((curve.sample(0.5, false)[0] - 2.25).abs() < 1e-6);
+}
+
+#[test]
+fn gltf_rejects_nan_times() {
+    let buffer = float_bytes(&[0.0, ::std::f32::NAN, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    let json = format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "nodes": [{{ "name": "root" }}],
+        "skins": [{{ "joints": [0] }}],
+        "animations": [{{
+            "channels": [{{ "sampler": 0, "target": {{ "node": 0, "path": "rotation" }} }}],
+            "samplers": [{{ "input": 0, "output": 1 }}]
+        }}],
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR" }},
+            {{ "bufferView": 0, "byteOffset": 8, "componentType": 5126, "count": 2, "type": "VEC4" }}
+        ],
+        "bufferViews": [{{ "buffer": 0, "byteLength": 40 }}],
+        "buffers": [{{ "byteLength": 40, "uri": "data:application/octet-stream;base64,{}" }}]
+    }}"#, encode_base64(&buffer));
+    let document = Document::from_slice(json.as_bytes(), None).unwrap();
+
+    match load_animation(&document, 0, 0) {
+        Err(GltfLoadError::InvalidSampler { animation: 0, sampler: 0 }) => {}
+        _ => panic!("expected an invalid sampler error"),
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct SkinnedVertex {
+    position: Vec3,
+    weights: [(usize, f32); 4],
+    count: usize,
+}
+
+impl VertexFromParts for SkinnedVertex {
+    fn from_parts(vertex: Vector3, _: Option<Vector2>, _: Option<Vector3>, weights: Option<&[JointWeight]>) -> Option<SkinnedVertex> {
+        let mut loaded = SkinnedVertex { position: vec3_from_vector3(&vertex), weights: [(0, 0.0); 4], count: 0 };
+        for weight in weights?.iter().take(4) {
+            loaded.weights[loaded.count] = (weight.joint, weight.weight);
+            loaded.count += 1;
+        }
+
+        Some(loaded)
+    }
+}
+
+#[test]
+fn gltf_skinned_mesh() {
+    let mut buffer = float_bytes(&[
+        0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0,
+        1.0, 0.0, 0.0, 0.0, 0.25, 0.75, 0.0, 0.0, 0.5, 0.0, 0.5, 0.0,
+    ]);
+    buffer.extend(&[0u8, 0, 0, 0, 1, 2, 0, 0, 3, 0, 1, 0]);
+    let json = format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0, "WEIGHTS_0": 1, "JOINTS_0": 2 }} }}] }}],
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }},
+            {{ "bufferView": 0, "byteOffset": 36, "componentType": 5126, "count": 3, "type": "VEC4" }},
+            {{ "bufferView": 0, "byteOffset": 84, "componentType": 5121, "count": 3, "type": "VEC4" }}
+        ],
+        "bufferViews": [{{ "buffer": 0, "byteLength": 96 }}],
+        "buffers": [{{ "byteLength": 96, "uri": "data:application/octet-stream;base64,{}" }}]
+    }}"#, encode_base64(&buffer));
+    let document = Document::from_slice(json.as_bytes(), None).unwrap();
+
+    let mesh = load_mesh::<SkinnedVertex>(&document, 0).unwrap();
+    assert!(mesh.vertices.len() == 3);
+    assert!(mesh.shapes.len() == 1);
+
+    let weights: Vec<&[(usize, f32)]> = mesh.vertices.iter().map(|x| &x.weights[..x.count]).collect();
+    assert!(weights[0] == &[(0, 1.0)][..]);
+    assert!(weights[1] == &[(1, 0.25), (2, 0.75)][..]);
+    // The zero weight in the second slot is dropped
+    assert!(weights[2] == &[(3, 0.5), (1, 0.5)][..]);
+    assert!(glm::distance(&mesh.vertices[2].position, &Vec3::new(0.0, 1.0, 0.0)) < 1e-6);
+}
+
+#[test]
+fn gltf_rejects_overflowing_accessors() {
+    let json = format!(r#"{{
+        "asset": {{ "version": "2.0" }},
+        "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": {}, "type": "VEC4" }},
+            {{ "bufferView": 0, "byteOffset": {}, "componentType": 5126, "count": 1, "type": "SCALAR" }}
+        ],
+        "bufferViews": [{{ "buffer": 0, "byteLength": 4 }}],
+        "buffers": [{{ "byteLength": 4, "uri": "data:application/octet-stream;base64,{}" }}]
+    }}"#, ::std::usize::MAX / 8, ::std::usize::MAX, encode_base64(&float_bytes(&[1.0])));
+    let document = Document::from_slice(json.as_bytes(), None).unwrap();
+
+    match document.read_floats(0) {
+        Err(GltfLoadError::InvalidAccessor(0)) => {}
+        _ => panic!("expected an invalid accessor error"),
+    }
+    match document.read_floats(1) {
+        Err(GltfLoadError::InvalidAccessor(1)) => {}
+        _ => panic!("expected an invalid accessor error"),
+    }
+}
@@ -0,0 +1,120 @@
+use super::GltfLoadError;
+use super::document::*;
+use collada_load::mesh::VertexFromParts;
+use collada_parser::collada::skin::JointWeight;
+use collada_parser::math::{Vector2, Vector3};
+use mesh::{self, triangulate::*};
+
+// Loads every primitive of a glTF mesh into one mesh. Joint indices are the indices into the
+// skin's joint list, which is the joint order that load_skeleton uses
+pub fn load_mesh<V: VertexFromParts + Copy>(document: &Document, mesh: usize) -> Result<mesh::Mesh<V>, GltfLoadError> {
+    let gltf_mesh = document.gltf.meshes.get(mesh).ok_or(GltfLoadError::MissingMesh(mesh))?;
+    let mut vertices = vec![];
+    let mut shapes = vec![];
+
+    for primitive in &gltf_mesh.primitives {
+        let base = vertices.len();
+        let attribute = |name: &str| primitive.attributes.get(name).cloned();
+
+        let positions = match attribute("POSITION") {
+            Some(accessor) => document.read_floats(accessor)?,
+            None => return Err(GltfLoadError::MissingAttribute { mesh, attribute: "POSITION" }),
+        };
+        let count = positions.len() / 3;
+
+        let normals = read_optional(document, attribute("NORMAL"), count * 3)?;
+        let tex_coords = read_optional(document, attribute("TEXCOORD_0"), count * 2)?;
+        let weights = read_optional(document, attribute("WEIGHTS_0"), count * 4)?;
+        let joints = match attribute("JOINTS_0") {
+            Some(accessor) => {
+                let joints = document.read_indices(accessor)?;
+                if joints.len() < count * 4 {
+                    return Err(GltfLoadError::InvalidAccessor(accessor));
+                }
+
+                Some(joints)
+            }
+            None => None,
+        };
+
+        let mut joint_weights = Vec::with_capacity(4);
+        for i in 0..count {
+            let vertex = Vector3::new(positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]);
+            let tex = tex_coords.as_ref().map(|x| Vector2::new(x[i * 2], x[i * 2 + 1]));
+            let normal = normals.as_ref().map(|x| Vector3::new(x[i * 3], x[i * 3 + 1], x[i * 3 + 2]));
+
+            joint_weights.clear();
+            if let (Some(joints), Some(weights)) = (&joints, &weights) {
+                for j in 0..4 {
+                    let weight = weights[i * 4 + j];
+                    if weight > 0.0 {
+                        joint_weights.push(JointWeight { joint: joints[i * 4 + j], weight });
+                    }
+                }
+            }
+
+            let weights = match joints.is_some() && weights.is_some() {
+                true => Some(joint_weights.as_slice()),
+                false => None,
+            };
+
+            match V::from_parts(vertex, tex, normal, weights) {
+                Some(vertex) => vertices.push(vertex),
+                None => return Err(GltfLoadError::InvalidVertex { mesh, vertex: base + i }),
+            }
+        }
+
+        let indices: Vec<usize> = match primitive.indices {
+            Some(accessor) => document.read_indices(accessor)?,
+            None => (0..count).collect(),
+        };
+
+        if indices.iter().any(|x| *x >= count) {
+            return Err(GltfLoadError::InvalidAccessor(primitive.indices.unwrap_or(0)));
+        }
+
+        let index = |i: usize| base + indices[i];
+        match primitive.mode {
+            POINTS => shapes.extend((0..indices.len()).map(|i| mesh::Shape::Point(index(i)))),
+            LINES => shapes.extend((0..indices.len() / 2).map(|i| mesh::Shape::Line(index(i * 2), index(i * 2 + 1)))),
+            LINE_STRIP | LINE_LOOP => {
+                let len = indices.len();
+                shapes.extend((1..len).map(|i| mesh::Shape::Line(index(i - 1), index(i))));
+                if primitive.mode == LINE_LOOP && len > 2 {
+                    shapes.push(mesh::Shape::Line(index(len - 1), index(0)));
+                }
+            }
+            TRIANGLES | TRIANGLE_STRIP | TRIANGLE_FAN => {
+                let triangles: Vec<[usize; 3]> = match primitive.mode {
+                    TRIANGLES => (0..indices.len() / 3).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]).collect(),
+                    TRIANGLE_STRIP => strip_triangles(indices.len()),
+                    _ => fan_triangles(indices.len()),
+                };
+
+                for triangle in triangles {
+                    let (a, b, c) = (index(triangle[0]), index(triangle[1]), index(triangle[2]));
+                    if a != b && b != c && a != c {
+                        shapes.push(mesh::Shape::Triangle(a, b, c));
+                    }
+                }
+            }
+            mode => return Err(GltfLoadError::UnsupportedPrimitive { mesh, mode }),
+        }
+    }
+
+    Ok(mesh::Mesh::new(vertices, shapes))
+}
+
+fn read_optional(document: &Document, accessor: Option<usize>, len: usize) -> Result<Option<Vec<f32>>, GltfLoadError> {
+    let accessor = match accessor {
+        Some(accessor) => accessor,
+        None => return Ok(None),
+    };
+
+    let values = document.read_floats(accessor)?;
+    if values.len() < len {
+        return Err(GltfLoadError::InvalidAccessor(accessor));
+    }
+
+    Ok(Some(values))
+}
@@ -0,0 +1,49 @@
+pub mod document;
+pub mod skeleton;
+pub mod mesh;
+pub mod animation;
+#[cfg(test)]
+mod gltf_tests;
+
+use std::error::Error;
+use std::fmt::{self, Display};
+
+#[derive(Clone, Debug)]
+pub enum GltfLoadError {
+    InvalidGlb,
+    MissingBuffer(usize),
+    UnsupportedUri(String),
+    InvalidAccessor(usize),
+    MissingNode(usize),
+    // The node is its own ancestor
+    CyclicNode(usize),
+    MissingSkin(usize),
+    MissingMesh(usize),
+    MissingAnimation(usize),
+    MissingAttribute { mesh: usize, attribute: &'static str },
+    UnsupportedPrimitive { mesh: usize, mode: u32 },
+    InvalidVertex { mesh: usize, vertex: usize },
+    InvalidSampler { animation: usize, sampler: usize },
+}
+
+impl Display for GltfLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfLoadError::InvalidGlb => write!(f, "invalid glb container"),
+            GltfLoadError::MissingBuffer(buffer) => write!(f, "missing data for buffer {}", buffer),
+            GltfLoadError::UnsupportedUri(uri) => write!(f, "unsupported buffer uri {}", uri),
+            GltfLoadError::InvalidAccessor(accessor) => write!(f, "invalid accessor {}", accessor),
+            GltfLoadError::MissingNode(node) => write!(f, "missing node {}", node),
+            GltfLoadError::CyclicNode(node) => write!(f, "node {} is its own ancestor", node),
+            GltfLoadError::MissingSkin(skin) => write!(f, "missing skin {}", skin),
+            GltfLoadError::MissingMesh(mesh) => write!(f, "missing mesh {}", mesh),
+            GltfLoadError::MissingAnimation(animation) => write!(f, "missing animation {}", animation),
+            GltfLoadError::MissingAttribute { mesh, attribute } => write!(f, "mesh {} is missing the {} attribute", mesh, attribute),
+            GltfLoadError::UnsupportedPrimitive { mesh, mode } => write!(f, "mesh {} has a primitive with unsupported mode {}", mesh, mode),
+            GltfLoadError::InvalidVertex { mesh, vertex } => write!(f, "vertex {} of mesh {} was rejected", vertex, mesh),
+            GltfLoadError::InvalidSampler { animation, sampler } => write!(f, "sampler {} of animation {} is invalid", sampler, animation),
+        }
+    }
+}
+
+impl Error for GltfLoadError {}
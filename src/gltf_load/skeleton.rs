@@ -0,0 +1,92 @@
+use super::GltfLoadError;
+use super::document::{Document, Node};
+use skeleton::{Skeleton, validate_tree, error::SkeletonError};
+use pose::*;
+use glm::{Mat4, Quat, Vec3};
+
+pub fn node_pose(node: &Node) -> Pose {
+    if let Some(matrix) = node.matrix {
+        return Pose::from_matrix(&Mat4::from_column_slice(&matrix));
+    }
+
+    let mut pose = Pose::pose_identity();
+    if let Some(t) = node.translation {
+        pose.translation = Vec3::new(t[0], t[1], t[2]);
+    }
+    if let Some(r) = node.rotation {
+        pose.rotation = Quat::new(r[3], r[0], r[1], r[2]);
+    }
+    if let Some(s) = node.scale {
+        pose.scale = Vec3::new(s[0], s[1], s[2]);
+    }
+
+    pose
+}
+
+// Finds the joint that a joint node is parented to and the combined pose of any non joint
+// nodes in between, which has to be applied before the node's own pose
+pub fn joint_parent(document: &Document, parents: &[Option<usize>], joints: &[usize], node: usize) -> Result<(Option<usize>, Pose), GltfLoadError> {
+    let mut prefix = Pose::pose_identity();
+    let mut current = parents[node];
+
+    // A walk longer than the node count means the node graph loops
+    for _ in 0..parents.len() {
+        let parent = match current {
+            Some(parent) => parent,
+            None => return Ok((None, prefix)),
+        };
+
+        if let Some(joint) = joints.iter().position(|x| *x == parent) {
+            return Ok((Some(joint), prefix));
+        }
+
+        prefix = node_pose(&document.gltf.nodes[parent]) * prefix;
+        current = parents[parent];
+    }
+
+    Err(GltfLoadError::CyclicNode(node))
+}
+
+// Builds a skeleton with the joints in the order of the skin so that joint indices in the
+// skinned meshes can be used directly
+pub fn load_skeleton(document: &Document, skin: usize) -> Result<Skeleton, GltfLoadError> {
+    let gltf_skin = document.gltf.skins.get(skin).ok_or(GltfLoadError::MissingSkin(skin))?;
+    let parents = document.node_parents();
+
+    let mut tree = Vec::with_capacity(gltf_skin.joints.len());
+    let mut poses = Vec::with_capacity(gltf_skin.joints.len());
+
+    for joint in &gltf_skin.joints {
+        let node = document.gltf.nodes.get(*joint).ok_or(GltfLoadError::MissingNode(*joint))?;
+        let (parent, prefix) = joint_parent(document, &parents, &gltf_skin.joints, *joint)?;
+
+        tree.push(parent);
+        poses.push(prefix * node_pose(node));
+    }
+
+    // Joints can still loop through each other
+    if let Err(SkeletonError::Cycle { joint }) = validate_tree(&tree) {
+        return Err(GltfLoadError::CyclicNode(gltf_skin.joints[joint]));
+    }
+
+    let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+
+    match gltf_skin.inverse_bind_matrices {
+        Some(accessor) => {
+            let values = document.read_floats(accessor)?;
+            if values.len() < gltf_skin.joints.len() * 16 {
+                return Err(GltfLoadError::InvalidAccessor(accessor));
+            }
+
+            skeleton.set_inv_bind_pose_iter(values.chunks(16).map(|x| Pose::from_matrix(&Mat4::from_column_slice(x))))
+                .map_err(|_| GltfLoadError::InvalidAccessor(accessor))?;
+        }
+        // Missing inverse bind matrices are identity matrices
+        None => {
+            skeleton.set_inv_bind_pose_iter((0..gltf_skin.joints.len()).map(|_| Pose::pose_identity()))
+                .map_err(|_| GltfLoadError::MissingSkin(skin))?;
+        }
+    }
+
+    Ok(skeleton)
+}
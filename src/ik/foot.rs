@@ -0,0 +1,272 @@
+use skeleton::Skeleton;
+use animation::Animation;
+use ik::two_bone::{TwoBoneChain, solve_two_bone};
+use math::rotation_between;
+use glm::{self, Vec3};
+
+// Where a leg learns whether its foot is planted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ContactSource {
+    // A scalar property track of the clip, the foot is planted above 0.5
+    Curve(String),
+    // Authored (start, end) times in seconds where the foot is planted
+    Markers(Vec<(f32, f32)>),
+}
+
+impl ContactSource {
+    pub fn is_planted(&self, animation: &Animation, time: f32) -> bool {
+        match self {
+            ContactSource::Curve(name) => {
+                let track = match animation.property_track(name) {
+                    Some(track) => track,
+                    None => return false,
+                };
+
+                let (a, b, f) = animation.frame_at_time(time);
+                let mut value = vec![];
+                track.interpolate_into(a, b, f, &mut value) && value.first().map_or(false, |x| *x > 0.5)
+            }
+            ContactSource::Markers(markers) => markers.iter().any(|(start, end)| time >= *start && time <= *end),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GroundHit {
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+// Lets the foot placement ask the game's collision for the ground under a foot
+pub trait GroundQuery {
+    // direction is normalized, hits further than max_distance are ignored
+    fn raycast(&self, origin: &Vec3, direction: &Vec3, max_distance: f32) -> Option<GroundHit>;
+}
+
+#[derive(Clone, Debug)]
+pub struct FootLeg {
+    // hip, knee and ankle
+    pub chain: TwoBoneChain,
+    pub contact: ContactSource,
+    // Height of the ankle above the sole
+    pub ankle_height: f32,
+    // World position the planted foot is held at
+    lock: Option<Vec3>,
+}
+
+impl FootLeg {
+    pub fn new(chain: TwoBoneChain, contact: ContactSource, ankle_height: f32) -> FootLeg {
+        FootLeg {
+            chain,
+            contact,
+            ankle_height,
+            lock: None,
+        }
+    }
+
+    pub fn lock(&self) -> Option<Vec3> {
+        self.lock
+    }
+}
+
+// Keeps feet from sliding and floating, run after the animation has set the local poses.
+// Planted feet are locked in world space and put on the ground, the pelvis drops so that the
+// lowest foot can reach and the feet turn to match the ground
+#[derive(Clone, Debug)]
+pub struct FootPlacement {
+    pub legs: Vec<FootLeg>,
+    pub pelvis: usize,
+    pub up: Vec3,
+    // How far above the animated ankle the rays start and how far below it they look
+    pub ray_height: f32,
+    pub ray_depth: f32,
+    pub align_to_ground: bool,
+    // How far the pelvis was moved along up in the last update
+    pelvis_offset: f32,
+}
+
+impl FootPlacement {
+    pub fn new(pelvis: usize, legs: Vec<FootLeg>) -> FootPlacement {
+        FootPlacement {
+            legs,
+            pelvis,
+            up: Vec3::new(0.0, 1.0, 0.0),
+            ray_height: 0.5,
+            ray_depth: 1.0,
+            align_to_ground: true,
+            pelvis_offset: 0.0,
+        }
+    }
+
+    pub fn pelvis_offset(&self) -> f32 {
+        self.pelvis_offset
+    }
+
+    // Forgets the locks, for teleports and cuts
+    pub fn reset(&mut self) {
+        for leg in self.legs.iter_mut() {
+            leg.lock = None;
+        }
+        self.pelvis_offset = 0.0;
+    }
+
+    // Adjusts the local poses for the animation sampled at time, the world poses are up to
+    // date afterwards
+    pub fn update<G: GroundQuery>(&mut self, skeleton: &mut Skeleton, animation: &Animation, time: f32, ground: &G) {
+        skeleton.update_world_poses();
+        let up = glm::normalize(&self.up);
+
+        let mut targets = Vec::with_capacity(self.legs.len());
+        for leg in self.legs.iter_mut() {
+            let ankle = match skeleton.world_pose_ref()[leg.chain.end] {
+                Some(pose) => pose,
+                None => {
+                    targets.push(None);
+                    continue;
+                }
+            };
+
+            let hit = ground.raycast(&(ankle.translation + up * self.ray_height), &-up, self.ray_height + self.ray_depth);
+            let planted = leg.contact.is_planted(animation, time);
+
+            let target = match (hit, planted) {
+                (Some(hit), true) => *leg.lock.get_or_insert(hit.point + up * leg.ankle_height),
+                (None, true) => *leg.lock.get_or_insert(ankle.translation),
+                // A swinging foot follows the animation but never goes through the ground
+                (hit, false) => {
+                    leg.lock = None;
+                    let lowest = hit.map(|x| glm::dot(&(x.point - ankle.translation), &up) + leg.ankle_height).unwrap_or(0.0);
+                    ankle.translation + up * lowest.max(0.0)
+                }
+            };
+
+            targets.push(Some((target, ankle, hit.filter(|_| planted))));
+        }
+
+        // Drop the pelvis as far as the lowest target needs, legs can't stretch to reach down
+        self.pelvis_offset = targets.iter()
+            .filter_map(|x| x.map(|(target, ankle, _)| glm::dot(&(target - ankle.translation), &up)))
+            .fold(0.0, |lowest: f32, x| lowest.min(x));
+        self.move_pelvis(skeleton, up * self.pelvis_offset);
+
+        for (leg, target) in self.legs.iter().zip(targets) {
+            let (target, ankle, hit) = match target {
+                Some(target) => target,
+                None => continue,
+            };
+
+            let position = |joint| skeleton.compute_joint_world_pose(joint).unwrap().translation;
+            let (hip, knee, foot) = (position(leg.chain.upper), position(leg.chain.middle), position(leg.chain.end));
+            // Keep the knee pointing where the animation had it
+            let pole = knee + (knee - (hip + foot) * 0.5);
+            solve_two_bone(skeleton, &leg.chain, &target, Some(&pole));
+
+            // Give the foot its animated world orientation back, tilted onto the ground
+            let mut rotation = ankle.rotation;
+            if let (Some(hit), true) = (hit, self.align_to_ground) {
+                rotation = rotation_between(&up, &hit.normal) * rotation;
+            }
+            let current = skeleton.compute_joint_world_pose(leg.chain.end).unwrap().rotation;
+            skeleton.rotate_joint_world(leg.chain.end, &(rotation * glm::quat_conjugate(&current)));
+        }
+
+        skeleton.update_world_poses();
+    }
+
+    // Moves the pelvis by a world space offset
+    fn move_pelvis(&self, skeleton: &mut Skeleton, offset: Vec3) {
+        let offset = match skeleton.tree_ref()[self.pelvis].and_then(|x| skeleton.compute_joint_world_pose(x)) {
+            Some(parent) => {
+                let local = glm::quat_rotate_vec3(&glm::quat_conjugate(&parent.rotation), &offset);
+                Vec3::new(local.x / parent.scale.x, local.y / parent.scale.y, local.z / parent.scale.z)
+            }
+            None => offset,
+        };
+
+        if let Some(pose) = skeleton.joint_pose_mut(self.pelvis) {
+            pose.translation += offset;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pose::Pose;
+    use animation::property::PropertyTrack;
+
+    // Flat ground at height that reports any normal
+    struct Ground {
+        height: f32,
+        normal: Vec3,
+    }
+
+    impl GroundQuery for Ground {
+        fn raycast(&self, origin: &Vec3, direction: &Vec3, max_distance: f32) -> Option<GroundHit> {
+            let distance = (origin.y - self.height) / -direction.y;
+            match distance >= 0.0 && distance <= max_distance {
+                true => Some(GroundHit { point: origin + direction * distance, normal: self.normal }),
+                false => None,
+            }
+        }
+    }
+
+    // pelvis, then hip, knee and ankle for each leg, the ankles rest at y = 0.1
+    fn legs() -> Skeleton {
+        let tree = vec![None, Some(0), Some(1), Some(2), Some(0), Some(4), Some(5)];
+        let poses = vec![
+            Pose::only_trans(0.0, 1.1, 0.0),
+            Pose::only_trans(0.2, 0.0, 0.0), Pose::only_trans(0.0, -0.5, 0.05), Pose::only_trans(0.0, -0.5, -0.05),
+            Pose::only_trans(-0.2, 0.0, 0.0), Pose::only_trans(0.0, -0.5, 0.05), Pose::only_trans(0.0, -0.5, -0.05),
+        ];
+        Skeleton::from_tree_pose(tree, poses)
+    }
+
+    fn walk() -> Animation {
+        let poses = legs().pose_ref().to_vec();
+        let mut animation = Animation::new(7, None);
+        animation.add_frame(&poses, 0.0);
+        animation.add_frame(&poses, 1.0);
+        animation.add_property_track(PropertyTrack::from_values("left_contact", 1, vec![1.0, 1.0]));
+        animation
+    }
+
+    #[test]
+    fn contact_sources() {
+        let animation = walk();
+        assert!(ContactSource::Curve("left_contact".into()).is_planted(&animation, 0.5));
+        assert!(!ContactSource::Curve("right_contact".into()).is_planted(&animation, 0.5));
+        assert!(ContactSource::Markers(vec![(0.2, 0.4)]).is_planted(&animation, 0.3));
+        assert!(!ContactSource::Markers(vec![(0.2, 0.4)]).is_planted(&animation, 0.5));
+    }
+
+    #[test]
+    fn feet_follow_ground() {
+        let animation = walk();
+        let mut skeleton = legs();
+        let left = FootLeg::new(TwoBoneChain { upper: 1, middle: 2, end: 3 }, ContactSource::Curve("left_contact".into()), 0.1);
+        let right = FootLeg::new(TwoBoneChain { upper: 4, middle: 5, end: 6 }, ContactSource::Markers(vec![]), 0.1);
+        let mut placement = FootPlacement::new(0, vec![left, right]);
+
+        // The ground is lower than the animation expects
+        let normal = glm::normalize(&Vec3::new(0.0, 1.0, 0.2));
+        let ground = Ground { height: -0.2, normal };
+        placement.update(&mut skeleton, &animation, 0.0, &ground);
+
+        let lock = placement.legs[0].lock().unwrap();
+        assert!((lock.y + 0.1).abs() < 1e-4);
+        assert!((placement.pelvis_offset() + 0.2).abs() < 1e-4);
+        assert!(glm::distance(&skeleton.joint_world_pose(3).unwrap().translation, &lock) < 1e-3);
+        assert!(placement.legs[1].lock().is_none());
+
+        // The planted foot is tilted onto the slope
+        let foot_up = glm::quat_rotate_vec3(&skeleton.joint_world_pose(3).unwrap().rotation, &Vec3::new(0.0, 1.0, 0.0));
+        assert!(glm::distance(&foot_up, &normal) < 1e-4);
+
+        // Moving the character doesn't drag the planted foot along
+        skeleton.pose_ref_mut().copy_from_slice(legs().pose_ref());
+        skeleton.joint_pose_mut(0).unwrap().translation.x += 0.05;
+        placement.update(&mut skeleton, &animation, 0.5, &ground);
+        assert!(glm::distance(&skeleton.joint_world_pose(3).unwrap().translation, &lock) < 1e-3);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod two_bone;
+pub mod foot;
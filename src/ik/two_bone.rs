@@ -0,0 +1,107 @@
+use skeleton::Skeleton;
+use math::{rotation_between, clampf32};
+use glm::{self, Vec3};
+
+// A chain of three joints like hip, knee and ankle where each is a child of the one before
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct TwoBoneChain {
+    pub upper: usize,
+    pub middle: usize,
+    pub end: usize,
+}
+
+fn position(skeleton: &Skeleton, joint: usize) -> Vec3 {
+    skeleton.compute_joint_world_pose(joint).map(|x| x.translation).unwrap_or_else(|| glm::zero())
+}
+
+fn angle(a: &Vec3, b: &Vec3) -> f32 {
+    clampf32(glm::dot(&glm::normalize(a), &glm::normalize(b)), -1.0, 1.0).acos()
+}
+
+// Turns the upper and middle joints so the end joint reaches target, or gets as close as the
+// bone lengths allow. The middle joint bends towards pole when one is given, otherwise the
+// current bend plane is kept. Only the local poses change, returns whether the target was
+// in reach
+pub fn solve_two_bone(skeleton: &mut Skeleton, chain: &TwoBoneChain, target: &Vec3, pole: Option<&Vec3>) -> bool {
+    let a = position(skeleton, chain.upper);
+    let b = position(skeleton, chain.middle);
+    let c = position(skeleton, chain.end);
+
+    let upper = glm::distance(&a, &b);
+    let lower = glm::distance(&b, &c);
+    if upper < 1e-6 || lower < 1e-6 {
+        return false;
+    }
+
+    let wanted = glm::distance(&a, target);
+    let reachable = wanted <= upper + lower && wanted >= (upper - lower).abs();
+    let reach = clampf32(wanted, (upper - lower).abs() + 1e-4, upper + lower - 1e-4);
+
+    // Bend the middle joint until the chain spans the wanted distance
+    let axis = match (glm::cross(&(a - b), &(c - b)), pole) {
+        (axis, _) if glm::length(&axis) > 1e-6 => Some(axis),
+        // A straight chain bends in the plane of the pole
+        (_, Some(pole)) => Some(glm::cross(&(a - b), &(*pole - b))),
+        _ => None,
+    };
+    if let Some(axis) = axis.filter(|x| glm::length(x) > 1e-6) {
+        let current = angle(&(a - b), &(c - b));
+        let bent = clampf32((upper * upper + lower * lower - reach * reach) / (2.0 * upper * lower), -1.0, 1.0).acos();
+        let turn = glm::quat_angle_axis(bent - current, &glm::normalize(&axis));
+        skeleton.rotate_joint_world(chain.middle, &turn);
+    }
+
+    // Swing the whole chain onto the target
+    let c = position(skeleton, chain.end);
+    let turn = rotation_between(&(c - a), &(target - a));
+    skeleton.rotate_joint_world(chain.upper, &turn);
+
+    // Twist around the line to the target until the middle joint faces the pole
+    if let Some(pole) = pole {
+        let line = target - a;
+        if glm::length(&line) > 1e-6 {
+            let line = glm::normalize(&line);
+            let flatten = |x: Vec3| x - line * glm::dot(&x, &line);
+            let b = position(skeleton, chain.middle);
+            let turn = rotation_between(&flatten(b - a), &flatten(*pole - a));
+            skeleton.rotate_joint_world(chain.upper, &turn);
+        }
+    }
+
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pose::Pose;
+
+    fn leg() -> Skeleton {
+        let tree = vec![None, Some(0), Some(1)];
+        let poses = vec![Pose::only_trans(0.0, 2.0, 0.0), Pose::only_trans(0.0, -1.0, 0.0), Pose::only_trans(0.0, -1.0, 0.0)];
+        Skeleton::from_tree_pose(tree, poses)
+    }
+
+    #[test]
+    fn reach_target_with_pole() {
+        let mut skeleton = leg();
+        let chain = TwoBoneChain { upper: 0, middle: 1, end: 2 };
+        let target = Vec3::new(0.0, 0.8, 0.3);
+        let pole = Vec3::new(0.0, 1.5, 2.0);
+
+        assert!(solve_two_bone(&mut skeleton, &chain, &target, Some(&pole)));
+        skeleton.update_world_poses();
+
+        let end = skeleton.joint_world_pose(2).unwrap().translation;
+        let knee = skeleton.joint_world_pose(1).unwrap().translation;
+        assert!(glm::distance(&end, &target) < 1e-3);
+        assert!((glm::distance(&knee, &Vec3::new(0.0, 2.0, 0.0)) - 1.0).abs() < 1e-4);
+        // The knee bends forward towards the pole
+        assert!(knee.z > 0.3);
+
+        // Out of reach stretches towards the target
+        assert!(!solve_two_bone(&mut skeleton, &chain, &Vec3::new(0.0, -3.0, 0.0), Some(&pole)));
+        skeleton.update_world_poses();
+        assert!(skeleton.joint_world_pose(2).unwrap().translation.y < 0.01);
+    }
+}
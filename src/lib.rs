@@ -13,6 +13,13 @@ pub mod animation;
 pub mod math;
 pub mod mesh;
 pub mod collada_load;
+pub mod gltf_load;
+pub mod gltf_export;
+pub mod bvh;
+pub mod bounds;
+pub mod coordinates;
+pub mod physics;
+pub mod ik;
 
 #[cfg(test)]
 mod glm_test;
\ No newline at end of file
@@ -1,5 +1,5 @@
 use glm::*;
-use collada_parser::math::Matrix4;
+use collada_parser::math::{Matrix4, Vector3};
 
 pub fn minf32(value: f32, max: f32) -> f32 {
     if value < max {
@@ -40,6 +40,12 @@ pub fn mat4_from_matrix4(m: &Matrix4) -> Mat4 {
     )
 }
 
+pub fn vec3_from_vector3(v: &Vector3) -> Vec3 {
+    let a = v.as_ref();
+
+    vec3(a[0], a[1], a[2])
+}
+
 pub fn print_mat4(mat: &Mat4) {
     for row in 0..4 {
         for column in 0..4 {
@@ -100,6 +106,76 @@ pub fn time_loop(mut time: f32, min: f32, max: f32) -> f32 {
     time
 }
 
+pub fn unit_axis(axis: usize) -> Vec3 {
+    match axis {
+        0 => vec3(1.0, 0.0, 0.0),
+        1 => vec3(0.0, 1.0, 0.0),
+        _ => vec3(0.0, 0.0, 1.0),
+    }
+}
+
+// Builds the rotation R = R(order[0]) * R(order[1]) * R(order[2]) where each order is an axis
+// index (0 = x, 1 = y, 2 = z) and the angles are in radians
+pub fn euler_to_quat(angles: &[f32; 3], order: &[usize; 3]) -> Quat {
+    let mut rotation = quat_identity();
+    for i in 0..3 {
+        rotation = rotation * quat_angle_axis(angles[i], &unit_axis(order[i]));
+    }
+
+    rotation
+}
+
+// Inverse of euler_to_quat, the axes of order have to be different
+pub fn quat_to_euler(rotation: &Quat, order: &[usize; 3]) -> [f32; 3] {
+    let (i, j, k) = (order[0], order[1], order[2]);
+    let m = quat_to_mat3(rotation);
+    let sign = match (i, j, k) {
+        (0, 1, 2) | (1, 2, 0) | (2, 0, 1) => 1.0,
+        _ => -1.0,
+    };
+
+    let sin_middle = clampf32(sign * m[(i, k)], -1.0, 1.0);
+    let middle = sin_middle.asin();
+
+    if sin_middle.abs() < 0.9999 {
+        let first = (-sign * m[(j, k)]).atan2(m[(k, k)]);
+        let last = (-sign * m[(i, j)]).atan2(m[(i, i)]);
+        [first, middle, last]
+    }
+    else {
+        // Gimbal lock, the first and last axis line up so the last angle is set to 0
+        let first = (sign * m[(k, j)]).atan2(m[(j, j)]);
+        [first, middle, 0.0]
+    }
+}
+
+// Shortest rotation that turns the direction of from into the direction of to, the identity
+// if either is zero
+pub fn rotation_between(from: &Vec3, to: &Vec3) -> Quat {
+    let (from_length, to_length) = (length(from), length(to));
+    if from_length < 1e-6 || to_length < 1e-6 {
+        return quat_identity();
+    }
+
+    let (from, to) = (from / from_length, to / to_length);
+    let cos = clampf32(dot(&from, &to), -1.0, 1.0);
+    let axis = cross(&from, &to);
+
+    if length(&axis) > 1e-6 {
+        return quat_angle_axis(cos.acos(), &normalize(&axis));
+    }
+    if cos > 0.0 {
+        return quat_identity();
+    }
+
+    // Opposite directions, turn half way around any perpendicular axis
+    let other = match from.x.abs() < 0.9 {
+        true => vec3(1.0, 0.0, 0.0),
+        false => vec3(0.0, 1.0, 0.0),
+    };
+    quat_angle_axis(::std::f32::consts::PI, &normalize(&cross(&from, &other)))
+}
+
 pub fn clampf32(value: f32, min: f32, max: f32) -> f32 {
     if value >= min && value <= max {
         value
@@ -147,6 +223,20 @@ mod tests {
         assert!((scale.z - e_scale.z).abs() <= epsilon());
     }
 
+    #[test]
+    fn euler_round_trip() {
+        let orders = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+        let angles = [0.3, -0.7, 1.1];
+
+        for order in &orders {
+            let rotation = math::euler_to_quat(&angles, order);
+            let result = math::quat_to_euler(&rotation, order);
+            for i in 0..3 {
+                assert!((result[i] - angles[i]).abs() < 1e-4);
+            }
+        }
+    }
+
     #[test]
     fn range_loop() {
         let time = 5.0;
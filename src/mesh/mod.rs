@@ -1,5 +1,38 @@
+pub mod simplify;
+pub mod triangulate;
+pub mod morph;
+
 // use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use glm::{Vec2, Vec3};
+use self::morph::MorphTarget;
+
+// Gives generic access to the attributes of a vertex so that meshes can be processed
+// without knowing the vertex layout. Attributes a vertex does not have can be left as the defaults
+pub trait MeshVertex: Copy {
+    fn position(&self) -> Vec3;
+
+    fn set_position(&mut self, position: Vec3);
+
+    fn normal(&self) -> Option<Vec3> {
+        None
+    }
+
+    fn set_normal(&mut self, _normal: Vec3) {}
+
+    fn tex_coord(&self) -> Option<Vec2> {
+        None
+    }
+
+    fn set_tex_coord(&mut self, _tex_coord: Vec2) {}
+
+    // Pairs of (joint index, weight)
+    fn joint_weights(&self) -> Vec<(usize, f32)> {
+        vec![]
+    }
+
+    fn set_joint_weights(&mut self, _weights: &[(usize, f32)]) {}
+}
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Shape {
@@ -34,14 +67,17 @@ impl Shape {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Mesh<VERTEX: Copy> {
     pub vertices: Vec<VERTEX>,
-    pub shapes: Vec<Shape>
+    pub shapes: Vec<Shape>,
+    #[serde(default)]
+    pub morph_targets: Vec<MorphTarget>,
 }
 
 impl<VERTEX: Copy> Mesh<VERTEX> {
     pub fn new(vertices: Vec<VERTEX>, shapes: Vec<Shape>) -> Mesh<VERTEX> {
         Mesh {
             vertices,
-            shapes
+            shapes,
+            morph_targets: vec![],
         }
     }
 
@@ -165,6 +201,19 @@ impl<VERTEX: Copy> Mesh<VERTEX> {
     }
 }
 
+impl<V: MeshVertex> Mesh<V> {
+    // Rewrites the joint weights with a table of old joint index -> new joint index, such as
+    // the one returned by Skeleton::reorder
+    pub fn remap_joints(&mut self, remap: &[usize]) {
+        let mut weights = vec![];
+        for vertex in self.vertices.iter_mut() {
+            weights.clear();
+            weights.extend(vertex.joint_weights().into_iter().map(|(joint, weight)| (remap[joint], weight)));
+            vertex.set_joint_weights(&weights);
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SingleShapeMesh<VERTEX: Copy> {
     pub vertices: Vec<VERTEX>,
@@ -0,0 +1,142 @@
+use super::{Mesh, MeshVertex};
+use glm::{self, Vec3};
+
+// Offset of a single vertex when its morph target is fully applied
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct MorphDelta {
+    pub vertex: usize,
+    pub position: Vec3,
+    pub normal: Vec3,
+}
+
+// Sparse blend shape, only the vertices that move are stored
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MorphTarget {
+    pub name: String,
+    pub deltas: Vec<MorphDelta>,
+}
+
+impl MorphTarget {
+    pub fn new(name: impl Into<String>, deltas: Vec<MorphDelta>) -> MorphTarget {
+        MorphTarget {
+            name: name.into(),
+            deltas,
+        }
+    }
+
+    // Builds the sparse deltas between two sets of (position, normal) pairs, vertices that
+    // move less than epsilon are left out
+    pub fn from_difference(name: impl Into<String>, base: &[(Vec3, Vec3)], target: &[(Vec3, Vec3)], epsilon: f32) -> MorphTarget {
+        assert!(base.len() == target.len());
+
+        let deltas = base.iter().zip(target.iter()).enumerate().filter_map(|(vertex, (base, target))| {
+            let position = target.0 - base.0;
+            let normal = target.1 - base.1;
+
+            if glm::length(&position) <= epsilon && glm::length(&normal) <= epsilon {
+                return None;
+            }
+
+            Some(MorphDelta {
+                vertex,
+                position,
+                normal,
+            })
+        }).collect();
+
+        MorphTarget::new(name, deltas)
+    }
+}
+
+impl<V: MeshVertex> Mesh<V> {
+    pub fn morph_target_index(&self, name: &str) -> Option<usize> {
+        self.morph_targets.iter().position(|x| x.name == name)
+    }
+
+    // Writes the vertices with the weighted morph targets applied into out, this should be done
+    // before skinning. Weights are matched to morph targets by index, missing weights count as 0
+    pub fn apply_morphs(&self, weights: &[f32], out: &mut Vec<V>) {
+        out.clear();
+        out.extend_from_slice(&self.vertices);
+
+        let mut normal_changed = vec![false; out.len()];
+
+        for (target, weight) in self.morph_targets.iter().zip(weights.iter()) {
+            if *weight == 0.0 {
+                continue;
+            }
+
+            for delta in &target.deltas {
+                let vertex = &mut out[delta.vertex];
+                let position = vertex.position() + delta.position * *weight;
+                vertex.set_position(position);
+
+                if let Some(normal) = vertex.normal() {
+                    vertex.set_normal(normal + delta.normal * *weight);
+                    normal_changed[delta.vertex] = true;
+                }
+            }
+        }
+
+        for (vertex, changed) in out.iter_mut().zip(normal_changed) {
+            if !changed {
+                continue;
+            }
+
+            if let Some(normal) = vertex.normal() {
+                let length = glm::length(&normal);
+                if length > 0.0 {
+                    vertex.set_normal(normal / length);
+                }
+            }
+        }
+    }
+
+    pub fn morphed_vertices(&self, weights: &[f32]) -> Vec<V> {
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        self.apply_morphs(weights, &mut vertices);
+
+        vertices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mesh::Shape;
+
+    #[derive(Copy, Clone, Debug)]
+    struct TestVertex {
+        position: Vec3,
+    }
+
+    impl MeshVertex for TestVertex {
+        fn position(&self) -> Vec3 {
+            self.position
+        }
+
+        fn set_position(&mut self, position: Vec3) {
+            self.position = position;
+        }
+    }
+
+    #[test]
+    fn weighted_morphs() {
+        let vertices = vec![
+            TestVertex { position: Vec3::new(0.0, 0.0, 0.0) },
+            TestVertex { position: Vec3::new(1.0, 0.0, 0.0) },
+        ];
+        let mut mesh = Mesh::new(vertices, vec![Shape::Line(0, 1)]);
+        let zero = Vec3::new(0.0, 0.0, 0.0);
+
+        mesh.morph_targets.push(MorphTarget::new("up", vec![MorphDelta { vertex: 1, position: Vec3::new(0.0, 2.0, 0.0), normal: zero }]));
+        mesh.morph_targets.push(MorphTarget::new("forward", vec![MorphDelta { vertex: 1, position: Vec3::new(0.0, 0.0, 1.0), normal: zero }]));
+
+        let morphed = mesh.morphed_vertices(&[0.5, 1.0]);
+
+        assert!(mesh.morph_target_index("forward") == Some(1));
+        assert!(morphed[0].position == zero);
+        assert!(morphed[1].position == Vec3::new(1.0, 1.0, 1.0));
+        assert!(mesh.vertices[1].position == Vec3::new(1.0, 0.0, 0.0));
+    }
+}
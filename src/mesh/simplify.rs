@@ -0,0 +1,709 @@
+use super::{Mesh, MeshVertex, Shape};
+use super::morph::{MorphTarget, MorphDelta};
+use glm::{self, Vec3};
+use of::OrderedFloat;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+
+// Collapses that turn a neighbouring triangle by more than this (as the cosine between the
+// old and new normal) are rejected so the surface never folds over itself
+const MIN_NORMAL_DOT: f32 = 0.2;
+
+// Boundary edges get an extra plane perpendicular to their face weighted by this amount
+// so that open borders keep their silhouette
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+#[derive(Copy, Clone, Debug)]
+pub enum LodTarget {
+    // Simplify until there are at most this many triangles
+    Triangles(usize),
+
+    // Simplify while the quadric error of the next collapse stays under this bound
+    Error(f32),
+}
+
+// Symmetric 4x4 matrix stored as the upper triangle
+#[derive(Copy, Clone, Debug)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn zero() -> Quadric {
+        Quadric([0.0; 10])
+    }
+
+    // Plane in the form ax + by + cz + d = 0
+    fn from_plane(a: f64, b: f64, c: f64, d: f64, weight: f64) -> Quadric {
+        Quadric([
+            a * a * weight, a * b * weight, a * c * weight, a * d * weight,
+            b * b * weight, b * c * weight, b * d * weight,
+            c * c * weight, c * d * weight,
+            d * d * weight,
+        ])
+    }
+
+    fn plane_through(normal: Vec3, point: Vec3, weight: f64) -> Quadric {
+        let (a, b, c) = (normal.x as f64, normal.y as f64, normal.z as f64);
+        let d = -(a * point.x as f64 + b * point.y as f64 + c * point.z as f64);
+
+        Quadric::from_plane(a, b, c, d, weight)
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        for i in 0..10 {
+            self.0[i] += other.0[i];
+        }
+    }
+
+    fn error(&self, point: Vec3) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (point.x as f64, point.y as f64, point.z as f64);
+
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z
+            + q[9]
+    }
+
+    // Solves for the point with the smallest error, returns None if the system is singular
+    fn optimal_point(&self) -> Option<Vec3> {
+        let q = &self.0;
+        let det = q[0] * (q[4] * q[7] - q[5] * q[5])
+            - q[1] * (q[1] * q[7] - q[5] * q[2])
+            + q[2] * (q[1] * q[5] - q[4] * q[2]);
+
+        if det.abs() < 1e-10 {
+            return None;
+        }
+
+        let (bx, by, bz) = (-q[3], -q[6], -q[8]);
+        let x = (bx * (q[4] * q[7] - q[5] * q[5]) - q[1] * (by * q[7] - q[5] * bz) + q[2] * (by * q[5] - q[4] * bz)) / det;
+        let y = (q[0] * (by * q[7] - q[5] * bz) - bx * (q[1] * q[7] - q[5] * q[2]) + q[2] * (q[1] * bz - by * q[2])) / det;
+        let z = (q[0] * (q[4] * bz - by * q[5]) - q[1] * (q[1] * bz - by * q[2]) + bx * (q[1] * q[5] - q[4] * q[2])) / det;
+
+        Some(Vec3::new(x as f32, y as f32, z as f32))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Collapse {
+    keep: usize,
+    remove: usize,
+    position: Vec3,
+    // How far along keep -> remove the new vertex sits, used to mix attributes
+    factor: f32,
+    cost: f64,
+}
+
+// Candidate ordered by smallest cost first, the stamps are used to throw away stale entries
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Candidate {
+    cost: Reverse<OrderedFloat<f64>>,
+    a: usize,
+    b: usize,
+    stamp_a: usize,
+    stamp_b: usize,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        self.cost.cmp(&other.cost)
+            .then_with(|| self.a.cmp(&other.a))
+            .then_with(|| self.b.cmp(&other.b))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Quadric error edge collapse simplifier. Vertices that share a position with another vertex
+// (uv or normal seams) are never moved, other vertices may still collapse onto them.
+// Only the triangles of the source mesh are kept.
+pub struct Simplifier<V: MeshVertex> {
+    vertices: Vec<V>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    removed: Vec<bool>,
+    stamps: Vec<usize>,
+    triangles: Vec<[usize; 3]>,
+    alive: Vec<bool>,
+    vertex_triangles: Vec<Vec<usize>>,
+    heap: BinaryHeap<Candidate>,
+    triangle_count: usize,
+    max_error: f64,
+    morph_names: Vec<String>,
+    // Dense (position, normal) deltas of every morph target, mixed like the vertices
+    morph_deltas: Vec<Vec<(Vec3, Vec3)>>,
+}
+
+impl<V: MeshVertex> Simplifier<V> {
+    pub fn new(mesh: &Mesh<V>) -> Simplifier<V> {
+        let vertices = mesh.vertices.clone();
+        let vertex_count = vertices.len();
+
+        let triangles: Vec<[usize; 3]> = mesh.shapes.iter().filter_map(|shape| match shape {
+            Shape::Triangle(a, b, c) if a != b && b != c && a != c => Some([*a, *b, *c]),
+            _ => None,
+        }).collect();
+
+        let mut vertex_triangles = vec![vec![]; vertex_count];
+        for (i, triangle) in triangles.iter().enumerate() {
+            for vertex in triangle {
+                vertex_triangles[*vertex].push(i);
+            }
+        }
+
+        let mut simplifier = Simplifier {
+            quadrics: vec![Quadric::zero(); vertex_count],
+            locked: find_seam_vertices(&vertices),
+            removed: vec![false; vertex_count],
+            stamps: vec![0; vertex_count],
+            alive: vec![true; triangles.len()],
+            triangle_count: triangles.len(),
+            vertices,
+            triangles,
+            vertex_triangles,
+            heap: BinaryHeap::new(),
+            max_error: 0.0,
+            morph_names: mesh.morph_targets.iter().map(|x| x.name.clone()).collect(),
+            morph_deltas: mesh.morph_targets.iter().map(|x| dense_deltas(x, vertex_count)).collect(),
+        };
+
+        simplifier.build_quadrics();
+
+        for vertex in 0..vertex_count {
+            for neighbour in simplifier.neighbours(vertex) {
+                if vertex < neighbour {
+                    simplifier.push_candidate(vertex, neighbour);
+                }
+            }
+        }
+
+        simplifier
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+
+    // The largest collapse error accepted so far
+    pub fn max_error(&self) -> f32 {
+        self.max_error as f32
+    }
+
+    fn build_quadrics(&mut self) {
+        let mut edge_uses: HashMap<(usize, usize), usize> = HashMap::new();
+
+        for triangle in &self.triangles {
+            let (p0, p1, p2) = self.triangle_positions(triangle);
+            let cross = glm::cross(&(p1 - p0), &(p2 - p0));
+            let area = glm::length(&cross);
+            if area <= 0.0 {
+                continue;
+            }
+
+            let quadric = Quadric::plane_through(cross / area, p0, area as f64 * 0.5);
+            for vertex in triangle {
+                self.quadrics[*vertex].add(&quadric);
+            }
+
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+                *edge_uses.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+
+        for triangle in &self.triangles {
+            let (p0, p1, p2) = self.triangle_positions(triangle);
+            let normal = glm::cross(&(p1 - p0), &(p2 - p0));
+
+            for i in 0..3 {
+                let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+                if edge_uses.get(&(a.min(b), a.max(b))) != Some(&1) {
+                    continue;
+                }
+
+                let pa = self.vertices[a].position();
+                let edge = self.vertices[b].position() - pa;
+                let perpendicular = glm::cross(&edge, &normal);
+                let length = glm::length(&perpendicular);
+                if length <= 0.0 {
+                    continue;
+                }
+
+                let quadric = Quadric::plane_through(perpendicular / length, pa, BOUNDARY_WEIGHT);
+                self.quadrics[a].add(&quadric);
+                self.quadrics[b].add(&quadric);
+            }
+        }
+    }
+
+    fn triangle_positions(&self, triangle: &[usize; 3]) -> (Vec3, Vec3, Vec3) {
+        (
+            self.vertices[triangle[0]].position(),
+            self.vertices[triangle[1]].position(),
+            self.vertices[triangle[2]].position(),
+        )
+    }
+
+    fn neighbours(&self, vertex: usize) -> Vec<usize> {
+        let mut neighbours = vec![];
+        for triangle in &self.vertex_triangles[vertex] {
+            if !self.alive[*triangle] {
+                continue;
+            }
+
+            for other in &self.triangles[*triangle] {
+                if *other != vertex && !neighbours.contains(other) {
+                    neighbours.push(*other);
+                }
+            }
+        }
+
+        neighbours
+    }
+
+    fn push_candidate(&mut self, a: usize, b: usize) {
+        let collapse = match self.evaluate(a, b) {
+            Some(collapse) => collapse,
+            None => return,
+        };
+
+        self.heap.push(Candidate {
+            cost: Reverse(OrderedFloat(collapse.cost)),
+            a,
+            b,
+            stamp_a: self.stamps[a],
+            stamp_b: self.stamps[b],
+        });
+    }
+
+    fn evaluate(&self, a: usize, b: usize) -> Option<Collapse> {
+        let (keep, remove) = match (self.locked[a], self.locked[b]) {
+            (true, true) => return None,
+            (false, true) => (b, a),
+            _ => (a, b),
+        };
+
+        let mut quadric = self.quadrics[keep];
+        quadric.add(&self.quadrics[remove]);
+
+        let start = self.vertices[keep].position();
+        let end = self.vertices[remove].position();
+
+        let mut options = vec![(start, 0.0)];
+        if !self.locked[keep] {
+            options.push((end, 1.0));
+            options.push(((start + end) * 0.5, 0.5));
+
+            if let Some(point) = quadric.optimal_point() {
+                let edge = end - start;
+                let length2 = glm::dot(&edge, &edge);
+                let factor = match length2 > 0.0 {
+                    true => glm::dot(&(point - start), &edge) / length2,
+                    false => 0.0,
+                };
+
+                if factor >= 0.0 && factor <= 1.0 {
+                    options.push((point, factor));
+                }
+            }
+        }
+
+        let mut best: Option<Collapse> = None;
+        for (position, factor) in options {
+            let cost = quadric.error(position).max(0.0);
+            if best.map(|best| cost < best.cost).unwrap_or(true) {
+                best = Some(Collapse { keep, remove, position, factor, cost });
+            }
+        }
+
+        best
+    }
+
+    fn flips(&self, collapse: &Collapse) -> bool {
+        for &(moved, other) in &[(collapse.keep, collapse.remove), (collapse.remove, collapse.keep)] {
+            for triangle in &self.vertex_triangles[moved] {
+                if !self.alive[*triangle] {
+                    continue;
+                }
+
+                let vertices = self.triangles[*triangle];
+                if vertices.contains(&other) {
+                    continue;
+                }
+
+                let (p0, p1, p2) = self.triangle_positions(&vertices);
+                let old_normal = glm::cross(&(p1 - p0), &(p2 - p0));
+
+                let moved_position = |i: usize, p: Vec3| if vertices[i] == moved { collapse.position } else { p };
+                let (n0, n1, n2) = (moved_position(0, p0), moved_position(1, p1), moved_position(2, p2));
+                let new_normal = glm::cross(&(n1 - n0), &(n2 - n0));
+
+                let old_length = glm::length(&old_normal);
+                let new_length = glm::length(&new_normal);
+                if new_length <= 0.0 {
+                    return true;
+                }
+
+                if old_length > 0.0 && glm::dot(&old_normal, &new_normal) / (old_length * new_length) < MIN_NORMAL_DOT {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    // Collapses edges until the target is reached or no more valid collapses remain
+    pub fn simplify(&mut self, target: LodTarget) {
+        while let Some(candidate) = self.heap.pop() {
+            match target {
+                LodTarget::Triangles(count) if self.triangle_count <= count => {
+                    self.heap.push(candidate);
+                    return;
+                }
+                LodTarget::Error(bound) if (candidate.cost.0).0 > bound as f64 => {
+                    self.heap.push(candidate);
+                    return;
+                }
+                _ => {}
+            }
+
+            let (a, b) = (candidate.a, candidate.b);
+            if self.removed[a] || self.removed[b] {
+                continue;
+            }
+
+            if candidate.stamp_a != self.stamps[a] || candidate.stamp_b != self.stamps[b] {
+                continue;
+            }
+
+            let collapse = match self.evaluate(a, b) {
+                Some(collapse) => collapse,
+                None => continue,
+            };
+
+            if self.flips(&collapse) {
+                continue;
+            }
+
+            self.apply(collapse);
+        }
+    }
+
+    fn apply(&mut self, collapse: Collapse) {
+        let Collapse { keep, remove, position, factor, cost } = collapse;
+
+        self.vertices[keep] = collapse_vertices(&self.vertices[keep], &self.vertices[remove], position, factor);
+        for deltas in self.morph_deltas.iter_mut() {
+            let (a, b) = (deltas[keep], deltas[remove]);
+            deltas[keep] = (a.0 * (1.0 - factor) + b.0 * factor, a.1 * (1.0 - factor) + b.1 * factor);
+        }
+        self.max_error = self.max_error.max(cost);
+
+        let removed_triangles = ::std::mem::replace(&mut self.vertex_triangles[remove], vec![]);
+        for triangle in removed_triangles {
+            if !self.alive[triangle] {
+                continue;
+            }
+
+            if self.triangles[triangle].contains(&keep) {
+                self.alive[triangle] = false;
+                self.triangle_count -= 1;
+                continue;
+            }
+
+            for vertex in self.triangles[triangle].iter_mut() {
+                if *vertex == remove {
+                    *vertex = keep;
+                }
+            }
+            self.vertex_triangles[keep].push(triangle);
+        }
+
+        let alive = &self.alive;
+        self.vertex_triangles[keep].retain(|triangle| alive[*triangle]);
+
+        let removed_quadric = self.quadrics[remove];
+        self.quadrics[keep].add(&removed_quadric);
+        self.removed[remove] = true;
+        self.stamps[keep] += 1;
+        self.stamps[remove] += 1;
+
+        for neighbour in self.neighbours(keep) {
+            self.push_candidate(keep, neighbour);
+        }
+    }
+
+    // Builds a mesh out of the remaining triangles, unused vertices are dropped and the morph
+    // targets are carried over to the vertices that are left
+    pub fn mesh(&self) -> Mesh<V> {
+        let mut remap = vec![None; self.vertices.len()];
+        let mut vertices = vec![];
+        let mut shapes = Vec::with_capacity(self.triangle_count);
+
+        for (i, triangle) in self.triangles.iter().enumerate() {
+            if !self.alive[i] {
+                continue;
+            }
+
+            let mut indices = [0; 3];
+            for (j, vertex) in triangle.iter().enumerate() {
+                indices[j] = match remap[*vertex] {
+                    Some(index) => index,
+                    None => {
+                        let index = vertices.len();
+                        vertices.push(self.vertices[*vertex]);
+                        remap[*vertex] = Some(index);
+                        index
+                    }
+                };
+            }
+
+            shapes.push(Shape::Triangle(indices[0], indices[1], indices[2]));
+        }
+
+        let mut mesh = Mesh::new(vertices, shapes);
+        mesh.morph_targets = self.morph_names.iter().zip(&self.morph_deltas).map(|(name, deltas)| {
+            let mut kept: Vec<MorphDelta> = remap.iter().zip(deltas).filter_map(|(new, delta)| match new {
+                Some(vertex) if *delta != (Vec3::zeros(), Vec3::zeros()) => Some(MorphDelta {
+                    vertex: *vertex,
+                    position: delta.0,
+                    normal: delta.1,
+                }),
+                _ => None,
+            }).collect();
+            kept.sort_by_key(|x| x.vertex);
+
+            MorphTarget::new(name.clone(), kept)
+        }).collect();
+
+        mesh
+    }
+}
+
+// Spreads a sparse morph target over every vertex, deltas of missing vertices are ignored
+fn dense_deltas(target: &MorphTarget, vertex_count: usize) -> Vec<(Vec3, Vec3)> {
+    let mut deltas = vec![(Vec3::zeros(), Vec3::zeros()); vertex_count];
+    for delta in &target.deltas {
+        if let Some(dense) = deltas.get_mut(delta.vertex) {
+            *dense = (delta.position, delta.normal);
+        }
+    }
+
+    deltas
+}
+
+// Vertices that have the exact same position as another vertex lie on a seam
+fn find_seam_vertices<V: MeshVertex>(vertices: &[V]) -> Vec<bool> {
+    let mut positions: HashMap<[u32; 3], usize> = HashMap::new();
+    for vertex in vertices {
+        let p = vertex.position();
+        *positions.entry([p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]).or_insert(0) += 1;
+    }
+
+    vertices.iter().map(|vertex| {
+        let p = vertex.position();
+        positions[&[p.x.to_bits(), p.y.to_bits(), p.z.to_bits()]] > 1
+    }).collect()
+}
+
+// Mixes the attributes of two vertices in the form "a * (1 - f) + b * f"
+fn collapse_vertices<V: MeshVertex>(a: &V, b: &V, position: Vec3, factor: f32) -> V {
+    let mut vertex = *a;
+    vertex.set_position(position);
+
+    if let (Some(na), Some(nb)) = (a.normal(), b.normal()) {
+        let normal = na * (1.0 - factor) + nb * factor;
+        let length = glm::length(&normal);
+        if length > 0.0 {
+            vertex.set_normal(normal / length);
+        }
+    }
+
+    if let (Some(ta), Some(tb)) = (a.tex_coord(), b.tex_coord()) {
+        vertex.set_tex_coord(ta * (1.0 - factor) + tb * factor);
+    }
+
+    let wa = a.joint_weights();
+    let wb = b.joint_weights();
+    if !wa.is_empty() || !wb.is_empty() {
+        let max_influences = wa.len().max(wb.len());
+        vertex.set_joint_weights(&merge_joint_weights(&wa, &wb, factor, max_influences));
+    }
+
+    vertex
+}
+
+// Mixes two sets of skinning weights in the form "a * (1 - f) + b * f", keeps the
+// largest max_influences weights and renormalizes them so they sum to 1
+pub fn merge_joint_weights(a: &[(usize, f32)], b: &[(usize, f32)], f: f32, max_influences: usize) -> Vec<(usize, f32)> {
+    let mut weights: Vec<(usize, f32)> = vec![];
+    let mut add = |joint: usize, weight: f32| {
+        match weights.iter_mut().find(|x| x.0 == joint) {
+            Some(existing) => existing.1 += weight,
+            None => weights.push((joint, weight)),
+        }
+    };
+
+    for (joint, weight) in a {
+        add(*joint, *weight * (1.0 - f));
+    }
+    for (joint, weight) in b {
+        add(*joint, *weight * f);
+    }
+
+    weights.retain(|x| x.1 > 0.0);
+    weights.sort_by(|x, y| y.1.partial_cmp(&x.1).unwrap_or(Ordering::Equal).then(x.0.cmp(&y.0)));
+    weights.truncate(max_influences);
+
+    let sum: f32 = weights.iter().map(|x| x.1).sum();
+    if sum > 0.0 {
+        for weight in weights.iter_mut() {
+            weight.1 /= sum;
+        }
+    }
+
+    weights
+}
+
+// Simplifies the mesh progressively, each level starts from the previous one so the targets
+// should be given from most to least detailed
+pub fn build_lod_chain<V: MeshVertex>(mesh: &Mesh<V>, targets: &[LodTarget]) -> Vec<Mesh<V>> {
+    let mut simplifier = Simplifier::new(mesh);
+
+    targets.iter().map(|target| {
+        simplifier.simplify(*target);
+        simplifier.mesh()
+    }).collect()
+}
+
+impl<V: MeshVertex> Mesh<V> {
+    pub fn simplified(&self, target: LodTarget) -> Mesh<V> {
+        let mut simplifier = Simplifier::new(self);
+        simplifier.simplify(target);
+        simplifier.mesh()
+    }
+
+    pub fn lod_chain(&self, targets: &[LodTarget]) -> Vec<Mesh<V>> {
+        build_lod_chain(self, targets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::Vec3;
+
+    #[derive(Copy, Clone, Debug)]
+    struct TestVertex {
+        position: Vec3,
+        weights: [(usize, f32); 2],
+    }
+
+    impl MeshVertex for TestVertex {
+        fn position(&self) -> Vec3 {
+            self.position
+        }
+
+        fn set_position(&mut self, position: Vec3) {
+            self.position = position;
+        }
+
+        fn joint_weights(&self) -> Vec<(usize, f32)> {
+            self.weights.to_vec()
+        }
+
+        fn set_joint_weights(&mut self, weights: &[(usize, f32)]) {
+            self.weights = [(0, 0.0); 2];
+            for (i, weight) in weights.iter().take(2).enumerate() {
+                self.weights[i] = *weight;
+            }
+        }
+    }
+
+    // Flat n x n grid of quads on the xz plane
+    fn grid(n: usize) -> Mesh<TestVertex> {
+        let mut vertices = vec![];
+        for z in 0..n + 1 {
+            for x in 0..n + 1 {
+                let joint = if x * 2 < n { 0 } else { 1 };
+                vertices.push(TestVertex {
+                    position: Vec3::new(x as f32, 0.0, z as f32),
+                    weights: [(joint, 1.0), (0, 0.0)],
+                });
+            }
+        }
+
+        let mut shapes = vec![];
+        let row = n + 1;
+        for z in 0..n {
+            for x in 0..n {
+                let i = z * row + x;
+                shapes.push(Shape::Triangle(i, i + row, i + 1));
+                shapes.push(Shape::Triangle(i + 1, i + row, i + row + 1));
+            }
+        }
+
+        Mesh::new(vertices, shapes)
+    }
+
+    #[test]
+    fn reduces_flat_grid() {
+        let mesh = grid(6);
+        let chain = mesh.lod_chain(&[LodTarget::Triangles(40), LodTarget::Triangles(10)]);
+
+        assert!(chain.len() == 2);
+        assert!(chain[0].shapes.len() <= 40);
+        assert!(chain[1].shapes.len() <= 10);
+        assert!(chain[1].shapes.len() > 0);
+        assert!(chain[1].only_triangles());
+    }
+
+    #[test]
+    fn zero_error_bound_keeps_borders() {
+        let mesh = grid(4);
+        let simplified = mesh.simplified(LodTarget::Error(1e-6));
+
+        // The grid is flat so interior vertices can go, but the outline has to stay
+        for corner in &[Vec3::new(0.0, 0.0, 0.0), Vec3::new(4.0, 0.0, 4.0)] {
+            assert!(simplified.vertices.iter().any(|v| v.position == *corner));
+        }
+        assert!(simplified.shapes.len() < mesh.shapes.len());
+    }
+
+    #[test]
+    fn keeps_morph_targets() {
+        let mut mesh = grid(6);
+        let raised: Vec<MorphDelta> = (0..mesh.vertices.len()).map(|vertex| MorphDelta {
+            vertex,
+            position: Vec3::new(0.0, 1.0, 0.0),
+            normal: Vec3::zeros(),
+        }).collect();
+        mesh.morph_targets = vec![MorphTarget::new("raise", raised), MorphTarget::new("empty", vec![])];
+
+        let simplified = mesh.simplified(LodTarget::Triangles(10));
+
+        assert!(simplified.morph_targets.len() == 2);
+        assert!(simplified.morph_target_index("raise") == Some(0));
+        let raise = &simplified.morph_targets[0];
+        assert!(raise.deltas.len() == simplified.vertices.len());
+        for delta in &raise.deltas {
+            assert!(glm::distance(&delta.position, &Vec3::new(0.0, 1.0, 0.0)) < 1e-5);
+        }
+        assert!(simplified.morph_targets[1].deltas.is_empty());
+    }
+
+    #[test]
+    fn merged_weights_are_normalized() {
+        let weights = merge_joint_weights(&[(0, 1.0)], &[(1, 0.5), (2, 0.5)], 0.5, 2);
+        let sum: f32 = weights.iter().map(|x| x.1).sum();
+
+        assert!(weights.len() == 2);
+        assert!(weights[0].0 == 0);
+        assert!((sum - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,162 @@
+use glm::{self, Vec2, Vec3};
+
+// Triangulates a simple polygon by ear clipping, works for concave polygons. Returns indices into
+// the given positions wound in the same direction as the polygon. Falls back to a fan if
+// no ear can be found (self intersecting or degenerate polygons)
+pub fn triangulate_polygon(positions: &[Vec3]) -> Vec<[usize; 3]> {
+    let count = positions.len();
+    if count < 3 {
+        return vec![];
+    }
+    if count == 3 {
+        return vec![[0, 1, 2]];
+    }
+
+    let points = project_polygon(positions);
+    let mut remaining: Vec<usize> = (0..count).collect();
+    let mut triangles = Vec::with_capacity(count - 2);
+
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let ear = (0..len).find(|i| {
+            let prev = remaining[(i + len - 1) % len];
+            let current = remaining[*i];
+            let next = remaining[(i + 1) % len];
+
+            is_ear(&points, &remaining, prev, current, next)
+        });
+
+        let i = match ear {
+            Some(i) => i,
+            None => {
+                triangles.extend(fan_triangles(len).into_iter().map(|t| [remaining[t[0]], remaining[t[1]], remaining[t[2]]]));
+                return triangles;
+            }
+        };
+
+        triangles.push([remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]]);
+        remaining.remove(i);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+// Triangles of a strip with `count` vertices, every second triangle is flipped to keep the winding
+pub fn strip_triangles(count: usize) -> Vec<[usize; 3]> {
+    if count < 3 {
+        return vec![];
+    }
+
+    (0..count - 2).map(|i| match i % 2 == 0 {
+        true => [i, i + 1, i + 2],
+        false => [i + 1, i, i + 2],
+    }).collect()
+}
+
+// Triangles of a fan with `count` vertices around the first vertex
+pub fn fan_triangles(count: usize) -> Vec<[usize; 3]> {
+    if count < 3 {
+        return vec![];
+    }
+
+    (1..count - 1).map(|i| [0, i, i + 1]).collect()
+}
+
+// Projects the polygon onto the plane that it is most aligned with such that it is
+// counter clockwise in 2d
+fn project_polygon(positions: &[Vec3]) -> Vec<Vec2> {
+    // Newell's method
+    let mut normal = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..positions.len() {
+        let a = positions[i];
+        let b = positions[(i + 1) % positions.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+
+    let abs = Vec3::new(normal.x.abs(), normal.y.abs(), normal.z.abs());
+    positions.iter().map(|p| {
+        if abs.x >= abs.y && abs.x >= abs.z {
+            match normal.x >= 0.0 {
+                true => Vec2::new(p.y, p.z),
+                false => Vec2::new(p.z, p.y),
+            }
+        }
+        else if abs.y >= abs.z {
+            match normal.y >= 0.0 {
+                true => Vec2::new(p.z, p.x),
+                false => Vec2::new(p.x, p.z),
+            }
+        }
+        else {
+            match normal.z >= 0.0 {
+                true => Vec2::new(p.x, p.y),
+                false => Vec2::new(p.y, p.x),
+            }
+        }
+    }).collect()
+}
+
+fn cross2(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn is_ear(points: &[Vec2], remaining: &[usize], prev: usize, current: usize, next: usize) -> bool {
+    let (a, b, c) = (points[prev], points[current], points[next]);
+
+    // Reflex or degenerate corner
+    if cross2(b - a, c - b) <= 0.0 {
+        return false;
+    }
+
+    !remaining.iter()
+        .filter(|i| **i != prev && **i != current && **i != next)
+        .any(|i| point_in_triangle(points[*i], a, b, c))
+}
+
+fn point_in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let d0 = cross2(b - a, p - a);
+    let d1 = cross2(c - b, p - b);
+    let d2 = cross2(a - c, p - c);
+
+    d0 >= 0.0 && d1 >= 0.0 && d2 >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::Vec3;
+
+    fn area(positions: &[Vec3], triangles: &[[usize; 3]]) -> f32 {
+        triangles.iter().map(|t| {
+            let (a, b, c) = (positions[t[0]], positions[t[1]], positions[t[2]]);
+            glm::cross(&(b - a), &(c - a)).z * 0.5
+        }).sum()
+    }
+
+    #[test]
+    fn concave_polygon() {
+        // L shape with an area of 3
+        let positions = vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(2.0, 1.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(1.0, 2.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+        ];
+
+        let triangles = triangulate_polygon(&positions);
+        assert!(triangles.len() == 4);
+        assert!((area(&positions, &triangles) - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn strips_and_fans() {
+        assert!(strip_triangles(5) == vec![[0, 1, 2], [2, 1, 3], [2, 3, 4]]);
+        assert!(fan_triangles(5) == vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        assert!(strip_triangles(2).is_empty());
+    }
+}
@@ -0,0 +1,2 @@
+pub mod spring;
+pub mod ragdoll;
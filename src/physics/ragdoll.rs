@@ -0,0 +1,241 @@
+use skeleton::{Skeleton, order::topological_order, error::{SkeletonError, MissingFinalPose}};
+use pose::{Pose, interpolate_poses};
+
+// The side of a physics engine the ragdoll talks to, bodies are whatever ids the engine uses
+pub trait RagdollPhysics {
+    // Moves a body to a world transform, for the kinematic bodies of an animated character
+    // and for placing the bodies when the simulation takes over
+    fn set_body_transform(&mut self, body: usize, transform: &Pose);
+
+    // The simulated world transform of a body, None if the engine doesn't know it
+    fn body_transform(&self, body: usize) -> Option<Pose>;
+}
+
+// A rigid body that drives a joint
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct RagdollBody {
+    pub joint: usize,
+    pub body: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RagdollMode {
+    // The animation drives the bodies
+    Animated,
+    // Blending from the animation into the simulation, the value is the time spent blending
+    ToSimulated(f32),
+    // The bodies drive the skeleton
+    Simulated,
+    // Blending from the last simulated pose back into the animation
+    ToAnimated(f32),
+}
+
+// Hands a skeleton over to a physics engine and back. Joints without a body keep their local
+// pose and follow their parent
+#[derive(Clone, Debug)]
+pub struct Ragdoll {
+    pub bodies: Vec<RagdollBody>,
+    // Seconds it takes to blend between the animation and the simulation
+    pub blend_time: f32,
+    mode: RagdollMode,
+    // Parent before child order of the skeleton the ragdoll was made for
+    order: Vec<usize>,
+    body_of: Vec<Option<usize>>,
+    // Local poses the blend back to the animation starts from
+    simulated: Vec<Pose>,
+    animated: Vec<Pose>,
+}
+
+impl Ragdoll {
+    pub fn new(skeleton: &Skeleton, bodies: Vec<RagdollBody>, blend_time: f32) -> Result<Ragdoll, SkeletonError> {
+        let order = topological_order(skeleton.tree_ref())?;
+        let mut body_of = vec![None; skeleton.bone_count()];
+        for body in &bodies {
+            match body_of.get_mut(body.joint) {
+                Some(body_of) => *body_of = Some(body.body),
+                None => return Err(SkeletonError::JointOutOfRange { joint: body.joint }),
+            }
+        }
+
+        Ok(Ragdoll {
+            bodies,
+            blend_time,
+            mode: RagdollMode::Animated,
+            order,
+            body_of,
+            simulated: vec![],
+            animated: vec![],
+        })
+    }
+
+    pub fn mode(&self) -> RagdollMode {
+        self.mode
+    }
+
+    // Places the bodies on the current animated pose and starts blending into the simulation
+    pub fn start_simulation<P: RagdollPhysics>(&mut self, skeleton: &mut Skeleton, physics: &mut P) {
+        self.export(skeleton, physics);
+        self.mode = RagdollMode::ToSimulated(0.0);
+    }
+
+    // Starts blending from where the simulation left the skeleton back into the animation
+    pub fn stop_simulation(&mut self, skeleton: &Skeleton) {
+        self.simulated.clear();
+        self.simulated.extend_from_slice(skeleton.pose_ref());
+        self.mode = RagdollMode::ToAnimated(0.0);
+    }
+
+    // Writes the world poses of the joints with bodies to the physics engine
+    pub fn export<P: RagdollPhysics>(&self, skeleton: &mut Skeleton, physics: &mut P) {
+        skeleton.update_world_poses();
+
+        for body in &self.bodies {
+            if let Some(Some(pose)) = skeleton.world_pose_ref().get(body.joint) {
+                physics.set_body_transform(body.body, &Pose::without_scale(pose.translation, pose.rotation));
+            }
+        }
+    }
+
+    // Replaces the local poses with the simulated ones
+    pub fn import<P: RagdollPhysics>(&self, skeleton: &mut Skeleton, physics: &P) -> Result<(), MissingFinalPose> {
+        let local = skeleton.pose_ref().to_vec();
+        let mut world = local.clone();
+
+        for joint in self.order.iter().cloned() {
+            let animated = match skeleton.tree_ref()[joint] {
+                Some(parent) => world[parent] * local[joint],
+                None => local[joint],
+            };
+
+            world[joint] = match self.body_of[joint].and_then(|x| physics.body_transform(x)) {
+                // Bodies don't scale, keep the animated scale
+                Some(body) => Pose { scale: animated.scale, ..body },
+                None => animated,
+            };
+            skeleton.set_joint_world_pose(joint, world[joint]);
+        }
+
+        skeleton.poses_from_world_poses()?;
+        skeleton.mark_all_dirty();
+        Ok(())
+    }
+
+    // Call after the animation has set the local poses. Drives the bodies, the skeleton or a
+    // blend of both depending on the mode, the world poses are up to date afterwards
+    pub fn update<P: RagdollPhysics>(&mut self, skeleton: &mut Skeleton, physics: &mut P, dt: f32) -> Result<(), MissingFinalPose> {
+        match self.mode {
+            RagdollMode::Animated => {
+                self.export(skeleton, physics);
+                return Ok(());
+            }
+            RagdollMode::Simulated => self.import(skeleton, physics)?,
+            RagdollMode::ToSimulated(elapsed) => {
+                let elapsed = elapsed + dt;
+                self.animated.clear();
+                self.animated.extend_from_slice(skeleton.pose_ref());
+                self.import(skeleton, physics)?;
+
+                let factor = self.blend_factor(elapsed);
+                let simulated = skeleton.pose_ref().to_vec();
+                interpolate_poses(&self.animated, &simulated, skeleton.pose_ref_mut(), factor);
+                self.mode = match factor < 1.0 {
+                    true => RagdollMode::ToSimulated(elapsed),
+                    false => RagdollMode::Simulated,
+                };
+            }
+            RagdollMode::ToAnimated(elapsed) => {
+                let elapsed = elapsed + dt;
+                let factor = self.blend_factor(elapsed);
+                self.animated.clear();
+                self.animated.extend_from_slice(skeleton.pose_ref());
+                interpolate_poses(&self.simulated, &self.animated, skeleton.pose_ref_mut(), factor);
+                self.mode = match factor < 1.0 {
+                    true => RagdollMode::ToAnimated(elapsed),
+                    false => RagdollMode::Animated,
+                };
+            }
+        }
+
+        skeleton.update_world_poses();
+        Ok(())
+    }
+
+    fn blend_factor(&self, elapsed: f32) -> f32 {
+        match self.blend_time > 0.0 {
+            true => (elapsed / self.blend_time).min(1.0),
+            false => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{self, Vec3};
+    use std::collections::HashMap;
+
+    // Bodies fall at a constant speed
+    #[derive(Default)]
+    struct FakePhysics {
+        bodies: HashMap<usize, Pose>,
+    }
+
+    impl FakePhysics {
+        fn step(&mut self, drop: f32) {
+            for pose in self.bodies.values_mut() {
+                pose.translation.y -= drop;
+            }
+        }
+    }
+
+    impl RagdollPhysics for FakePhysics {
+        fn set_body_transform(&mut self, body: usize, transform: &Pose) {
+            self.bodies.insert(body, *transform);
+        }
+
+        fn body_transform(&self, body: usize) -> Option<Pose> {
+            self.bodies.get(&body).cloned()
+        }
+    }
+
+    #[test]
+    fn blend_into_simulation_and_back() {
+        let tree = vec![None, Some(0), Some(1)];
+        let poses = vec![Pose::only_trans(0.0, 2.0, 0.0), Pose::only_trans(1.0, 0.0, 0.0), Pose::only_trans(1.0, 0.0, 0.0)];
+        let mut skeleton = Skeleton::from_tree_pose(tree, poses.clone());
+        let bodies = vec![RagdollBody { joint: 0, body: 10 }, RagdollBody { joint: 1, body: 11 }];
+        let mut ragdoll = Ragdoll::new(&skeleton, bodies, 1.0).unwrap();
+        let mut physics = FakePhysics::default();
+
+        ragdoll.update(&mut skeleton, &mut physics, 0.5).unwrap();
+        assert!(glm::distance(&physics.bodies[&11].translation, &Vec3::new(1.0, 2.0, 0.0)) < 1e-6);
+
+        ragdoll.start_simulation(&mut skeleton, &mut physics);
+        physics.step(1.0);
+        ragdoll.update(&mut skeleton, &mut physics, 0.5).unwrap();
+        // Half way between the animation and the bodies one unit lower
+        assert!((skeleton.joint_world_pose(0).unwrap().translation.y - 1.5).abs() < 1e-5);
+
+        ragdoll.update(&mut skeleton, &mut physics, 0.5).unwrap();
+        assert!(ragdoll.mode() == RagdollMode::Simulated);
+        // The joint without a body follows its parent
+        assert!(glm::distance(&skeleton.joint_world_pose(2).unwrap().translation, &Vec3::new(2.0, 1.0, 0.0)) < 1e-5);
+
+        ragdoll.stop_simulation(&skeleton);
+        skeleton.pose_ref_mut().copy_from_slice(&poses);
+        ragdoll.update(&mut skeleton, &mut physics, 1.0).unwrap();
+        assert!(ragdoll.mode() == RagdollMode::Animated);
+        assert!((skeleton.joint_world_pose(0).unwrap().translation.y - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reject_missing_joint() {
+        let skeleton = Skeleton::from_tree_pose(vec![None, Some(0)], vec![Pose::pose_identity(); 2]);
+        let bodies = vec![RagdollBody { joint: 0, body: 10 }, RagdollBody { joint: 2, body: 11 }];
+
+        match Ragdoll::new(&skeleton, bodies, 1.0) {
+            Err(error) => assert!(error == SkeletonError::JointOutOfRange { joint: 2 }),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+}
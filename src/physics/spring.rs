@@ -0,0 +1,329 @@
+use skeleton::Skeleton;
+use pose::Pose;
+use math::{rotation_between, clampf32};
+use glm::{self, Vec3};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct SpringSettings {
+    // How much of the way back to the animated position a particle moves each step, 0 to 1
+    pub stiffness: f32,
+    // Fraction of the velocity lost each step, 0 to 1
+    pub damping: f32,
+    pub gravity: Vec3,
+    // Radius of the particles against colliders
+    pub radius: f32,
+}
+
+impl Default for SpringSettings {
+    fn default() -> SpringSettings {
+        SpringSettings {
+            stiffness: 0.1,
+            damping: 0.1,
+            gravity: Vec3::new(0.0, -9.81, 0.0),
+            radius: 0.0,
+        }
+    }
+}
+
+// Shapes the particles are pushed out of, given relative to a joint
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Collider {
+    Sphere { joint: usize, offset: Vec3, radius: f32 },
+    Capsule { joint: usize, start: Vec3, end: Vec3, radius: f32 },
+}
+
+impl Collider {
+    // Moves point out of the collider, the world poses of the skeleton have to be built
+    fn push_out(&self, skeleton: &Skeleton, point: &mut Vec3, extra: f32) {
+        let (joint, start, end, radius) = match *self {
+            Collider::Sphere { joint, offset, radius } => (joint, offset, offset, radius),
+            Collider::Capsule { joint, start, end, radius } => (joint, start, end, radius),
+        };
+        let world = match skeleton.world_pose_ref()[joint] {
+            Some(pose) => pose,
+            None => return,
+        };
+
+        let (start, end) = (world.transform_point(start), world.transform_point(end));
+        let segment = end - start;
+        let along = match glm::dot(&segment, &segment) {
+            x if x > 1e-12 => clampf32(glm::dot(&(*point - start), &segment) / x, 0.0, 1.0),
+            _ => 0.0,
+        };
+        let closest = start + segment * along;
+
+        let offset = *point - closest;
+        let distance = glm::length(&offset);
+        let radius = radius + extra;
+        if distance < radius && distance > 1e-6 {
+            *point = closest + offset * (radius / distance);
+        }
+    }
+}
+
+// A run of joints where each is the parent of the next. The first joint stays where the
+// animation puts it and the rest are simulated
+#[derive(Clone, Debug)]
+pub struct SpringChain {
+    pub joints: Vec<usize>,
+    pub settings: SpringSettings,
+    // Extra simulated point relative to the last joint so the last bone can swing too
+    pub tip: Option<Vec3>,
+    positions: Vec<Vec3>,
+    previous: Vec<Vec3>,
+    lengths: Vec<f32>,
+}
+
+impl SpringChain {
+    pub fn new(joints: Vec<usize>, settings: SpringSettings) -> SpringChain {
+        SpringChain {
+            joints,
+            settings,
+            tip: None,
+            positions: vec![],
+            previous: vec![],
+            lengths: vec![],
+        }
+    }
+
+    pub fn with_tip(mut self, tip: Vec3) -> SpringChain {
+        self.tip = Some(tip);
+        self
+    }
+
+    // World positions of the simulated points, empty before the first update
+    pub fn positions_ref(&self) -> &[Vec3] {
+        self.positions.as_slice()
+    }
+
+    // Where the animation puts the points, the world poses of the skeleton have to be built
+    fn targets(&self, skeleton: &Skeleton) -> Vec<Vec3> {
+        let world = |joint: usize| skeleton.world_pose_ref()[joint].unwrap_or_else(Pose::pose_identity);
+
+        let mut targets: Vec<Vec3> = self.joints.iter().map(|x| world(*x).translation).collect();
+        if let (Some(tip), Some(last)) = (self.tip, self.joints.last()) {
+            targets.push(world(*last).transform_point(tip));
+        }
+
+        targets
+    }
+
+    fn reset(&mut self, targets: &[Vec3]) {
+        self.positions = targets.to_vec();
+        self.previous = targets.to_vec();
+        self.lengths = (0..targets.len()).map(|i| match i {
+            0 => 0.0,
+            _ => glm::distance(&targets[i - 1], &targets[i]),
+        }).collect();
+    }
+
+    fn step(&mut self, skeleton: &Skeleton, colliders: &[Collider], targets: &[Vec3], dt: f32) {
+        let settings = self.settings;
+        self.positions[0] = targets[0];
+        self.previous[0] = targets[0];
+
+        for i in 1..self.positions.len() {
+            let position = self.positions[i];
+            let velocity = (position - self.previous[i]) * (1.0 - settings.damping);
+            self.previous[i] = position;
+
+            let mut position = position + velocity + settings.gravity * dt * dt;
+            position += (targets[i] - position) * settings.stiffness;
+
+            // Keep the bone length, the parent is already solved
+            let parent = self.positions[i - 1];
+            let direction = position - parent;
+            let length = glm::length(&direction);
+            if length > 1e-6 {
+                position = parent + direction * (self.lengths[i] / length);
+            }
+
+            // Last so nothing ends up inside a collider, the length is only kept roughly there
+            for collider in colliders {
+                collider.push_out(skeleton, &mut position, settings.radius);
+            }
+
+            self.positions[i] = position;
+        }
+    }
+
+    // Rotates the joints so that every bone points at its simulated child
+    fn write_back(&self, skeleton: &mut Skeleton) {
+        for i in 0..self.positions.len() - 1 {
+            let joint = self.joints[i];
+            // The parents were just changed so the cached world poses are stale
+            let world = match skeleton.compute_joint_world_pose(joint) {
+                Some(pose) => pose,
+                None => return,
+            };
+            let child = match self.joints.get(i + 1) {
+                Some(child) => world.transform_point(skeleton.pose_ref()[*child].translation),
+                None => world.transform_point(self.tip.unwrap()),
+            };
+
+            let turn = rotation_between(&(child - world.translation), &(self.positions[i + 1] - world.translation));
+            skeleton.rotate_joint_world(joint, &turn);
+        }
+    }
+}
+
+// Secondary motion for hair, tails and the like, run after the animation has set the local
+// poses. The simulation steps at a fixed rate no matter how often update is called
+#[derive(Clone, Debug)]
+pub struct SpringSolver {
+    pub chains: Vec<SpringChain>,
+    pub colliders: Vec<Collider>,
+    pub timestep: f32,
+    // Steps beyond this in one update are dropped so a long frame can't stall the solver,
+    // has to be at least 1
+    pub max_steps: usize,
+    accumulator: f32,
+}
+
+impl SpringSolver {
+    pub fn new(timestep: f32) -> SpringSolver {
+        assert!(timestep > 0.0);
+
+        SpringSolver {
+            chains: vec![],
+            colliders: vec![],
+            timestep,
+            max_steps: 8,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn add_chain(&mut self, chain: SpringChain) -> usize {
+        self.chains.push(chain);
+        self.chains.len() - 1
+    }
+
+    pub fn add_collider(&mut self, collider: Collider) {
+        self.colliders.push(collider);
+    }
+
+    // Snaps every chain back to the animated pose on the next update, for teleports and cuts
+    pub fn reset(&mut self) {
+        for chain in self.chains.iter_mut() {
+            chain.positions.clear();
+        }
+        self.accumulator = 0.0;
+    }
+
+    // Advances the simulation by dt seconds and applies it to the skeleton's local poses, the
+    // world poses are up to date afterwards
+    pub fn update(&mut self, skeleton: &mut Skeleton, dt: f32) {
+        assert!(self.max_steps > 0);
+
+        self.accumulator += dt;
+        let mut steps = 0;
+        while self.accumulator >= self.timestep && steps < self.max_steps {
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+        // Only time that didn't fit in max_steps is dropped
+        if self.accumulator >= self.timestep {
+            self.accumulator = 0.0;
+        }
+
+        for chain in self.chains.iter_mut() {
+            if chain.joints.is_empty() {
+                continue;
+            }
+
+            // Chains below an earlier chain have to see where it was moved to
+            skeleton.update_world_poses();
+            let targets = chain.targets(skeleton);
+            if chain.positions.len() != targets.len() {
+                chain.reset(&targets);
+            }
+
+            for _ in 0..steps {
+                chain.step(skeleton, &self.colliders, &targets, self.timestep);
+            }
+            chain.write_back(skeleton);
+        }
+
+        skeleton.update_world_poses();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tail() -> Skeleton {
+        let tree = vec![None, Some(0), Some(1), Some(2)];
+        let poses = vec![Pose::pose_identity(), Pose::only_trans(1.0, 0.0, 0.0), Pose::only_trans(1.0, 0.0, 0.0), Pose::only_trans(1.0, 0.0, 0.0)];
+        Skeleton::from_tree_pose(tree, poses)
+    }
+
+    #[test]
+    fn tail_droops_and_keeps_length() {
+        let mut skeleton = tail();
+        let settings = SpringSettings { stiffness: 0.0, damping: 0.2, ..SpringSettings::default() };
+        let mut solver = SpringSolver::new(1.0 / 60.0);
+        solver.add_chain(SpringChain::new(vec![1, 2, 3], settings));
+
+        // Less than a step does nothing
+        solver.update(&mut skeleton, 0.001);
+        assert!(skeleton.joint_world_pose(3).unwrap().translation.y.abs() < 1e-6);
+
+        for _ in 0..120 {
+            solver.update(&mut skeleton, 1.0 / 60.0);
+        }
+
+        let world: Vec<Vec3> = (1..4).map(|x| skeleton.joint_world_pose(x).unwrap().translation).collect();
+        assert!(world[2].y < -0.5);
+        assert!((glm::distance(&world[0], &world[1]) - 1.0).abs() < 1e-3);
+        assert!((glm::distance(&world[1], &world[2]) - 1.0).abs() < 1e-3);
+        for (joint, position) in world.iter().zip(solver.chains[0].positions_ref()) {
+            assert!(glm::distance(joint, position) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn collider_holds_up_chain() {
+        let mut skeleton = tail();
+        let settings = SpringSettings { stiffness: 0.0, damping: 0.2, ..SpringSettings::default() };
+        let mut solver = SpringSolver::new(1.0 / 60.0);
+        solver.add_chain(SpringChain::new(vec![1, 2, 3], settings));
+        solver.add_collider(Collider::Capsule { joint: 0, start: Vec3::new(1.0, -1.0, -1.0), end: Vec3::new(1.0, -1.0, 1.0), radius: 0.9 });
+
+        for _ in 0..120 {
+            solver.update(&mut skeleton, 1.0 / 60.0);
+        }
+
+        let capsule_line = |p: Vec3| ((p.x - 1.0).powi(2) + (p.y + 1.0).powi(2)).sqrt();
+        for position in solver.chains[0].positions_ref() {
+            assert!(capsule_line(*position) > 0.9 - 1e-3);
+        }
+    }
+
+    #[test]
+    fn later_chains_follow_earlier_ones() {
+        let mut skeleton = tail();
+        let settings = SpringSettings { stiffness: 0.0, damping: 0.2, ..SpringSettings::default() };
+        let mut solver = SpringSolver::new(1.0 / 60.0);
+        solver.add_chain(SpringChain::new(vec![1, 2], settings));
+        solver.add_chain(SpringChain::new(vec![2, 3], settings));
+
+        for _ in 0..10 {
+            solver.update(&mut skeleton, 1.0 / 60.0);
+        }
+
+        // The second chain starts where the first one moved joint 2
+        let joint = skeleton.joint_world_pose(2).unwrap().translation;
+        assert!(joint.y < 0.0);
+        assert!(glm::distance(&joint, &solver.chains[1].positions_ref()[0]) < 1e-4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_max_steps_panics() {
+        let mut skeleton = tail();
+        let mut solver = SpringSolver::new(1.0 / 60.0);
+        solver.max_steps = 0;
+        solver.update(&mut skeleton, 1.0);
+    }
+}
@@ -0,0 +1,137 @@
+use super::Skeleton;
+use pose::Pose;
+
+// A named point that follows a joint, for props like weapons or hats
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Socket {
+    pub name: String,
+    pub joint: usize,
+    // Relative to the joint
+    pub offset: Pose,
+}
+
+// A skeleton whose roots follow a joint of another skeleton, like a cape rig on a character
+#[derive(Clone, Debug)]
+pub struct Attachment {
+    pub name: String,
+    pub joint: usize,
+    pub offset: Pose,
+    pub skeleton: Skeleton,
+}
+
+impl Skeleton {
+    // Replaces any socket with the same name
+    pub fn add_socket(&mut self, name: impl Into<String>, joint: usize, offset: Pose) {
+        assert!(joint < self.tree.len());
+
+        let name = name.into();
+        self.sockets.retain(|x| x.name != name);
+        self.sockets.push(Socket { name, joint, offset });
+    }
+
+    pub fn remove_socket(&mut self, name: &str) -> Option<Socket> {
+        let index = self.sockets.iter().position(|x| x.name == name)?;
+        Some(self.sockets.remove(index))
+    }
+
+    pub fn socket(&self, name: &str) -> Option<&Socket> {
+        self.sockets.iter().find(|x| x.name == name)
+    }
+
+    pub fn sockets_ref(&self) -> &[Socket] {
+        self.sockets.as_slice()
+    }
+
+    // Uses the joint's cached world pose when it has one, otherwise computes it
+    pub fn socket_world_pose(&self, name: &str) -> Option<Pose> {
+        let socket = self.socket(name)?;
+        let joint = match self.world_pose[socket.joint] {
+            Some(pose) => pose,
+            None => self.compute_joint_world_pose(socket.joint)?,
+        };
+
+        Some(joint * socket.offset)
+    }
+
+    // Attaches the roots of skeleton to joint, replacing any attachment with the same name.
+    // The attachment's world poses are built along with this skeleton's
+    pub fn attach(&mut self, name: impl Into<String>, joint: usize, offset: Pose, skeleton: Skeleton) {
+        assert!(joint < self.tree.len());
+
+        let name = name.into();
+        self.attachments.retain(|x| x.name != name);
+        self.attachments.push(Attachment { name, joint, offset, skeleton });
+    }
+
+    pub fn detach(&mut self, name: &str) -> Option<Skeleton> {
+        let index = self.attachments.iter().position(|x| x.name == name)?;
+        Some(self.attachments.remove(index).skeleton)
+    }
+
+    pub fn attachment(&self, name: &str) -> Option<&Skeleton> {
+        self.attachments.iter().find(|x| x.name == name).map(|x| &x.skeleton)
+    }
+
+    pub fn attachment_mut(&mut self, name: &str) -> Option<&mut Skeleton> {
+        self.attachments.iter_mut().find(|x| x.name == name).map(|x| &mut x.skeleton)
+    }
+
+    pub fn attachments_ref(&self) -> &[Attachment] {
+        self.attachments.as_slice()
+    }
+
+    // Builds the world poses as if every root had base as its parent
+    pub fn build_world_poses_from(&mut self, base: Pose) {
+        self.build_world_poses_linear();
+
+        for pose in self.world_pose.iter_mut() {
+            if let Some(pose) = pose {
+                *pose = base * *pose;
+            }
+        }
+
+        self.update_attachments();
+    }
+
+    // Rebuilds the world poses of the attachments from this skeleton's world poses
+    pub(super) fn update_attachments(&mut self) {
+        for i in 0..self.attachments.len() {
+            let joint = self.attachments[i].joint;
+            let base = match self.world_pose[joint] {
+                Some(pose) => pose,
+                None => continue,
+            };
+
+            let attachment = &mut self.attachments[i];
+            attachment.skeleton.build_world_poses_from(base * attachment.offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{self, Vec3};
+
+    #[test]
+    fn sockets_and_attachments() {
+        let tree = vec![None, Some(0)];
+        let poses = vec![Pose::only_trans(0.0, 1.0, 0.0), Pose::only_rot(glm::quat_angle_axis(::std::f32::consts::FRAC_PI_2, &Vec3::new(0.0, 0.0, 1.0)))];
+        let mut body = Skeleton::from_tree_pose(tree, poses);
+
+        body.add_socket("hand", 1, Pose::only_trans(1.0, 0.0, 0.0));
+        let hand = body.socket_world_pose("hand").unwrap();
+        assert!(glm::distance(&hand.translation, &Vec3::new(0.0, 2.0, 0.0)) < 1e-5);
+
+        let cape = Skeleton::from_tree_pose(vec![None, Some(0)], vec![Pose::pose_identity(), Pose::only_trans(0.0, -1.0, 0.0)]);
+        body.attach("cape", 0, Pose::only_trans(0.0, 0.0, -0.5), cape);
+        body.build_world_poses();
+
+        let cape = body.attachment("cape").unwrap();
+        let bottom = cape.joint_world_pose(1).unwrap();
+        assert!(glm::distance(&bottom.translation, &Vec3::new(0.0, 0.0, -0.5)) < 1e-5);
+
+        assert!(body.detach("cape").is_some());
+        assert!(body.attachment("cape").is_none());
+    }
+}
@@ -0,0 +1,127 @@
+use super::Skeleton;
+use pose::Pose;
+
+impl Skeleton {
+    pub fn mark_dirty(&mut self, id: usize) {
+        self.dirty[id] = true;
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        for dirty in self.dirty.iter_mut() {
+            *dirty = true;
+        }
+    }
+
+    pub fn is_dirty(&self, id: usize) -> bool {
+        self.dirty[id]
+    }
+
+    pub(super) fn clear_dirty(&mut self) {
+        for dirty in self.dirty.iter_mut() {
+            *dirty = false;
+        }
+    }
+
+    // True if the joint or one of its ancestors is dirty
+    fn dirty_chain(&self, mut id: usize) -> bool {
+        // Bounded by the joint count so a cyclic tree can't loop forever
+        for _ in 0..self.tree.len() {
+            if self.dirty[id] {
+                return true;
+            }
+
+            match self.tree[id] {
+                Some(parent) => id = parent,
+                None => return false,
+            }
+        }
+
+        false
+    }
+
+    // Rebuilds the world poses of the dirty joints and everything below them, the rest keep
+    // their cached world poses
+    pub fn update_world_poses(&mut self) {
+        if self.is_topologically_ordered() {
+            // Parents come first so dirtiness can be pushed down in the same pass
+            for joint in 0..self.tree.len() {
+                let stale = self.dirty[joint] || self.world_pose[joint].is_none() || match self.tree[joint] {
+                    Some(parent) => self.dirty[parent],
+                    None => false,
+                };
+
+                if stale {
+                    self.dirty[joint] = true;
+                    self.world_pose[joint] = Some(match self.tree[joint] {
+                        Some(parent) => self.world_pose[parent].unwrap() * self.pose[joint],
+                        None => self.pose[joint],
+                    });
+                }
+            }
+        }
+        else {
+            for joint in 0..self.tree.len() {
+                if self.dirty_chain(joint) {
+                    self.world_pose[joint] = None;
+                }
+            }
+
+            for joint in 0..self.tree.len() {
+                self.build_joint_world_pose(joint);
+            }
+        }
+
+        self.clear_dirty();
+        self.update_attachments();
+    }
+
+    // Computes the world pose of one joint from the local poses of its ancestors without
+    // touching the cached world poses
+    pub fn compute_joint_world_pose(&self, id: usize) -> Option<Pose> {
+        if id >= self.tree.len() {
+            return None;
+        }
+
+        let mut pose = self.pose[id];
+        let mut current = id;
+        for _ in 0..self.tree.len() {
+            match self.tree[current] {
+                Some(parent) => {
+                    pose = self.pose[parent] * pose;
+                    current = parent;
+                }
+                None => return Some(pose),
+            }
+        }
+
+        // The tree has a cycle
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{self, Vec3};
+
+    #[test]
+    fn update_dirty_subtree() {
+        // 0 - 1 - 2 is an arm, 3 is a leg
+        let tree = vec![None, Some(0), Some(1), Some(0)];
+        let poses = vec![Pose::only_trans(0.0, 1.0, 0.0); 4];
+        let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+        skeleton.build_world_poses();
+        assert!(!skeleton.is_dirty(1));
+
+        skeleton.joint_pose_mut(1).unwrap().translation = Vec3::new(2.0, 0.0, 0.0);
+        assert!(skeleton.is_dirty(1) && !skeleton.is_dirty(2));
+
+        let hand = skeleton.compute_joint_world_pose(2).unwrap();
+        assert!(glm::distance(&hand.translation, &Vec3::new(2.0, 2.0, 0.0)) < 1e-6);
+
+        skeleton.update_world_poses();
+        assert!(!skeleton.is_dirty(1));
+        assert!(glm::distance(&skeleton.joint_world_pose(2).unwrap().translation, &Vec3::new(2.0, 2.0, 0.0)) < 1e-6);
+        assert!(glm::distance(&skeleton.joint_world_pose(3).unwrap().translation, &Vec3::new(0.0, 2.0, 0.0)) < 1e-6);
+    }
+}
@@ -29,4 +29,62 @@ impl error::Error for MissingFinalPose {
     fn description(&self) -> &str {
         "Missing final pose"
     }
-}
\ No newline at end of file
+}
+// Why a tree of parent indices can't be used as a skeleton
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkeletonError {
+    // The tree and another per joint array have different lengths
+    LengthMismatch { joints: usize, values: usize },
+    ParentOutOfRange { joint: usize, parent: usize },
+    // The joint is its own ancestor
+    Cycle { joint: usize },
+    NoRoot,
+    MultipleRoots { first: usize, second: usize },
+    // A socket is on a joint that doesn't exist
+    SocketOutOfRange { joint: usize },
+    // A new joint order lists a joint twice or one that doesn't exist
+    InvalidOrder { joint: usize },
+    // Something refers to a joint that doesn't exist
+    JointOutOfRange { joint: usize },
+}
+
+impl fmt::Display for SkeletonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkeletonError::LengthMismatch { joints, values } => write!(f, "Skeleton has {} joints but {} values", joints, values),
+            SkeletonError::ParentOutOfRange { joint, parent } => write!(f, "Joint {} has parent {} which does not exist", joint, parent),
+            SkeletonError::Cycle { joint } => write!(f, "Joint {} is its own ancestor", joint),
+            SkeletonError::NoRoot => write!(f, "Skeleton has no root joint"),
+            SkeletonError::MultipleRoots { first, second } => write!(f, "Joints {} and {} are both roots", first, second),
+            SkeletonError::SocketOutOfRange { joint } => write!(f, "Socket is on joint {} which does not exist", joint),
+            SkeletonError::InvalidOrder { joint } => write!(f, "Joint order lists joint {} twice or it does not exist", joint),
+            SkeletonError::JointOutOfRange { joint } => write!(f, "Joint {} does not exist", joint),
+        }
+    }
+}
+
+impl error::Error for SkeletonError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeError {
+    Invalid(SkeletonError),
+    // A shared joint has a different parent than in an earlier skeleton
+    ParentMismatch(String),
+}
+
+impl From<SkeletonError> for MergeError {
+    fn from(error: SkeletonError) -> MergeError {
+        MergeError::Invalid(error)
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeError::Invalid(error) => write!(f, "Can't merge invalid skeleton: {}", error),
+            MergeError::ParentMismatch(joint) => write!(f, "Joint {} has different parents in the merged skeletons", joint),
+        }
+    }
+}
+
+impl error::Error for MergeError {}
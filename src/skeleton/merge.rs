@@ -0,0 +1,251 @@
+use super::{Skeleton, validate_tree, order::topological_order, error::MergeError};
+use std::collections::HashMap;
+
+pub struct MergedSkeleton {
+    pub skeleton: Skeleton,
+    // remaps[i][joint of skeletons[i]] = joint of the merged skeleton, use with
+    // Animation::remap_targets and Mesh::remap_joints
+    pub remaps: Vec<Vec<usize>>,
+}
+
+pub struct ExtractedSkeleton {
+    pub skeleton: Skeleton,
+    // Joint of the original skeleton for every joint of the extracted one
+    pub joints: Vec<usize>,
+    // Joint of the extracted skeleton for every joint of the original, None if it was left out
+    pub remap: Vec<Option<usize>>,
+}
+
+// Merges skeletons into one, joints with the same name become a single joint. Earlier
+// skeletons win, so the poses, inverse bind poses and sockets of a shared joint come from the
+// first skeleton that has it. The exception is a shared joint that was a root so far, it takes
+// the parent and pose of the first later skeleton that gives it a parent, which makes the
+// result independent of whether a part comes before the skeleton it attaches to. The merged
+// joints aren't necessarily parent before child ordered. Unnamed joints are never shared and
+// attachments aren't merged
+pub fn merge_skeletons(skeletons: &[&Skeleton]) -> Result<MergedSkeleton, MergeError> {
+    let mut tree = vec![];
+    let mut poses = vec![];
+    let mut inv_bind_poses = vec![];
+    let mut names: Vec<Option<String>> = vec![];
+    let mut by_name: HashMap<String, usize> = HashMap::new();
+    let mut remaps = Vec::with_capacity(skeletons.len());
+
+    for skeleton in skeletons {
+        let order = topological_order(&skeleton.tree)?;
+        let mut remap = vec![0; skeleton.tree.len()];
+        // Names are only shared between skeletons, duplicates within one stay separate joints
+        let mut added = vec![];
+
+        for joint in order {
+            // Parents come first so they are already remapped
+            let parent = skeleton.tree[joint].map(|x| remap[x]);
+            let existing = skeleton.names[joint].as_ref().and_then(|x| by_name.get(x).cloned());
+
+            remap[joint] = match existing {
+                Some(merged) => {
+                    if tree[merged].is_none() && parent.is_some() {
+                        tree[merged] = parent;
+                        poses[merged] = skeleton.pose[joint];
+                    }
+                    else if parent.is_some() && tree[merged] != parent {
+                        return Err(MergeError::ParentMismatch(skeleton.names[joint].clone().unwrap()));
+                    }
+                    merged
+                }
+                None => {
+                    let merged = tree.len();
+                    tree.push(parent);
+                    poses.push(skeleton.pose[joint]);
+                    inv_bind_poses.push(skeleton.inv_bind_pose[joint]);
+                    names.push(skeleton.names[joint].clone());
+                    if let Some(name) = &skeleton.names[joint] {
+                        added.push((name.clone(), merged));
+                    }
+
+                    merged
+                }
+            };
+        }
+
+        for (name, merged) in added {
+            by_name.entry(name).or_insert(merged);
+        }
+        remaps.push(remap);
+    }
+
+    // Adopted parents can close a loop when the skeletons disagree on which joint is above
+    validate_tree(&tree)?;
+
+    let mut merged = Skeleton::from_tree_pose(tree, poses);
+    merged.inv_bind_pose = inv_bind_poses;
+    merged.names = names;
+
+    for (skeleton, remap) in skeletons.iter().zip(&remaps) {
+        for socket in &skeleton.sockets {
+            if merged.socket(&socket.name).is_none() {
+                merged.add_socket(socket.name.clone(), remap[socket.joint], socket.offset);
+            }
+        }
+    }
+
+    Ok(MergedSkeleton {
+        skeleton: merged,
+        remaps,
+    })
+}
+
+impl Skeleton {
+    // Copies the joint and everything below it into a standalone skeleton. The new root gets
+    // the joint's world bind pose from its inverse bind pose so the copied inverse bind poses
+    // stay valid, without one it gets the world pose of the current local poses. Sockets on
+    // the copied joints are kept
+    pub fn extract_subtree(&self, joint: usize) -> Option<ExtractedSkeleton> {
+        let root_pose = match self.inv_bind_pose.get(joint)? {
+            Some(inv_bind_pose) => inv_bind_pose.inverse(),
+            None => self.compute_joint_world_pose(joint)?,
+        };
+        let order = topological_order(&self.tree).ok()?;
+
+        let mut remap = vec![None; self.tree.len()];
+        let mut joints = vec![];
+        for current in order {
+            let included = current == joint || self.tree[current].map(|x| remap[x].is_some()).unwrap_or(false);
+            if included {
+                remap[current] = Some(joints.len());
+                joints.push(current);
+            }
+        }
+
+        let tree = joints.iter().map(|x| match *x == joint {
+            true => None,
+            false => self.tree[*x].and_then(|parent| remap[parent]),
+        }).collect();
+        let poses = joints.iter().map(|x| match *x == joint {
+            true => root_pose,
+            false => self.pose[*x],
+        }).collect();
+
+        let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+        skeleton.inv_bind_pose = joints.iter().map(|x| self.inv_bind_pose[*x]).collect();
+        skeleton.names = joints.iter().map(|x| self.names[*x].clone()).collect();
+        for socket in &self.sockets {
+            if let Some(new) = remap[socket.joint] {
+                skeleton.add_socket(socket.name.clone(), new, socket.offset);
+            }
+        }
+
+        Some(ExtractedSkeleton {
+            skeleton,
+            joints,
+            remap,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use skeleton::error::SkeletonError;
+    use pose::Pose;
+    use glm::{self, Vec3};
+
+    fn named(tree: Vec<Option<usize>>, names: &[&str]) -> Skeleton {
+        let poses = vec![Pose::only_trans(0.0, 1.0, 0.0); tree.len()];
+        let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+        for (i, name) in names.iter().enumerate() {
+            skeleton.set_joint_name(i, *name);
+        }
+
+        skeleton
+    }
+
+    #[test]
+    fn merge_by_name() {
+        let body = named(vec![None, Some(0), Some(1)], &["hips", "spine", "neck"]);
+        let head = named(vec![None, Some(0), Some(1)], &["neck", "head", "jaw"]);
+
+        let merged = merge_skeletons(&[&body, &head]).unwrap();
+
+        assert!(merged.skeleton.bone_count() == 5);
+        assert!(merged.remaps[1] == vec![2, 3, 4]);
+        assert!(merged.skeleton.tree_ref()[3] == Some(2));
+        assert!(merged.skeleton.find_joint("jaw") == Some(4));
+
+        let wrong = named(vec![None, Some(0)], &["hips", "neck"]);
+        match merge_skeletons(&[&body, &wrong]) {
+            Err(MergeError::ParentMismatch(name)) => assert!(name == "neck"),
+            _ => panic!("expected a parent mismatch"),
+        }
+    }
+
+    #[test]
+    fn merge_part_first() {
+        let body = named(vec![None, Some(0), Some(1)], &["hips", "spine", "neck"]);
+        let mut head = named(vec![None, Some(0), Some(1)], &["neck", "head", "jaw"]);
+        head.joint_pose_mut(0).unwrap().translation = Vec3::new(0.0, 3.0, 0.0);
+
+        let merged = merge_skeletons(&[&head, &body]).unwrap();
+        let skeleton = &merged.skeleton;
+
+        assert!(skeleton.bone_count() == 5);
+        assert!(merged.remaps[0] == vec![0, 1, 2]);
+        assert!(merged.remaps[1] == vec![3, 4, 0]);
+        assert!(skeleton.tree_ref() == &[Some(4), Some(0), Some(1), None, Some(3)]);
+        assert!(skeleton.validate().is_ok());
+
+        // The neck takes its local pose from the body it was attached to
+        assert!(glm::distance(&skeleton.pose_ref()[0].translation, &Vec3::new(0.0, 1.0, 0.0)) < 1e-6);
+        let jaw = skeleton.compute_joint_world_pose(2).unwrap();
+        assert!(glm::distance(&jaw.translation, &Vec3::new(0.0, 5.0, 0.0)) < 1e-6);
+    }
+
+    #[test]
+    fn merge_duplicate_names() {
+        // Both arms end in a joint called end
+        let body = named(vec![None, Some(0), Some(0), Some(1), Some(2)], &["hips", "left", "right", "end", "end"]);
+        let prop = named(vec![None, Some(0)], &["end", "sword"]);
+
+        let merged = merge_skeletons(&[&body, &prop]).unwrap();
+
+        assert!(merged.skeleton.bone_count() == 6);
+        assert!(merged.remaps[0] == vec![0, 1, 2, 3, 4]);
+        assert!(merged.skeleton.tree_ref()[4] == Some(2));
+        // Later skeletons attach to the first joint with the name
+        assert!(merged.remaps[1] == vec![3, 5]);
+    }
+
+    #[test]
+    fn merge_cycle() {
+        let a = named(vec![None, Some(0)], &["spine", "neck"]);
+        let b = named(vec![None, Some(0)], &["neck", "spine"]);
+
+        match merge_skeletons(&[&a, &b]) {
+            Err(MergeError::Invalid(SkeletonError::Cycle { .. })) => {}
+            _ => panic!("expected a cycle"),
+        }
+    }
+
+    #[test]
+    fn extract_arm() {
+        let body = named(vec![None, Some(0), Some(1), Some(0)], &["hips", "arm", "hand", "leg"]);
+        let arm = body.extract_subtree(1).unwrap();
+
+        assert!(arm.joints == vec![1, 2]);
+        assert!(arm.remap == vec![None, Some(0), Some(1), None]);
+        assert!(arm.skeleton.tree_ref() == &[None, Some(0)]);
+        assert!(glm::distance(&arm.skeleton.pose_ref()[0].translation, &Vec3::new(0.0, 2.0, 0.0)) < 1e-6);
+    }
+
+    #[test]
+    fn extract_animated_arm() {
+        let mut body = named(vec![None, Some(0), Some(1)], &["hips", "arm", "hand"]);
+        body.build_inv_bind_poses();
+        body.joint_pose_mut(0).unwrap().translation = Vec3::new(5.0, 0.0, 0.0);
+
+        let arm = body.extract_subtree(1).unwrap();
+
+        // The root is where the arm was bound, not where the animation moved it
+        assert!(glm::distance(&arm.skeleton.pose_ref()[0].translation, &Vec3::new(0.0, 2.0, 0.0)) < 1e-5);
+    }
+}
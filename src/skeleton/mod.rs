@@ -1,30 +1,84 @@
 pub mod error;
+mod serialize;
+pub mod order;
+pub mod world;
+mod dirty;
+pub mod attachment;
+pub mod merge;
 
 use self::error::*;
+use self::attachment::{Socket, Attachment};
 use pose::*;
 use std::error::Error;
 
 pub type SkeletalPose = Vec<Pose>;
 
+#[derive(Clone, Debug)]
 pub struct Skeleton {
     tree: Vec<Option<usize>>,
     pose: SkeletalPose,
     world_pose: Vec<Option<Pose>>,
     inv_bind_pose: Vec<Option<Pose>>,
+    names: Vec<Option<String>>,
+    // Joints whose local pose changed since their world pose was last built
+    dirty: Vec<bool>,
+    sockets: Vec<Socket>,
+    attachments: Vec<Attachment>,
 }
 
 impl Skeleton {
+    // Panics if the tree has a cycle or a parent that doesn't exist, trees with several roots
+    // are allowed. Use validate_tree first on trees that come from outside data
     pub fn from_tree_pose(tree: Vec<Option<usize>>, pose: SkeletalPose) -> Skeleton {
         assert!(tree.len() == pose.len());
+        if let Err(error) = validate_tree(&tree) {
+            panic!("{}", error);
+        }
 
         let world_pose = vec![None; tree.len()];
         let inv_bind_pose = vec![None; tree.len()];
+        let names = vec![None; tree.len()];
+        let dirty = vec![true; tree.len()];
 
         Skeleton {
             tree,
             pose,
             world_pose,
-            inv_bind_pose
+            inv_bind_pose,
+            names,
+            dirty,
+            sockets: vec![],
+            attachments: vec![],
+        }
+    }
+
+    pub fn joint_name(&self, id: usize) -> Option<&str> {
+        self.names.get(id).and_then(|x| x.as_ref().map(|x| x.as_str()))
+    }
+
+    pub fn set_joint_name(&mut self, id: usize, name: impl Into<String>) {
+        self.names[id] = Some(name.into());
+    }
+
+    // Returns the first joint with the name
+    pub fn find_joint(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|x| x.as_ref().map(|x| x == name).unwrap_or(false))
+    }
+
+    pub fn names_ref(&self) -> &[Option<String>] {
+        self.names.as_slice()
+    }
+
+    // Checks that every parent exists, that the tree has no cycles and that there is
+    // exactly one root
+    pub fn validate(&self) -> Result<(), SkeletonError> {
+        validate_tree(&self.tree)?;
+
+        let mut roots = self.tree.iter().enumerate().filter(|(_, parent)| parent.is_none()).map(|(i, _)| i);
+        match (roots.next(), roots.next()) {
+            (Some(_), None) => Ok(()),
+            (Some(first), Some(second)) => Err(SkeletonError::MultipleRoots { first, second }),
+            (None, _) => Err(SkeletonError::NoRoot),
         }
     }
 
@@ -40,7 +94,7 @@ impl Skeleton {
             }
         }
 
-        true
+        found
     }
 
     // Returns the first root with no parent
@@ -62,11 +116,13 @@ impl Skeleton {
         Some(self.pose[id])
     }
 
+    // Marks the joint as dirty
     pub fn joint_pose_mut(&mut self, id: usize) -> Option<&mut Pose> {
         if id >= self.tree.len() {
             return None;
         }
 
+        self.dirty[id] = true;
         Some(&mut self.pose[id])
     }
 
@@ -90,7 +146,9 @@ impl Skeleton {
         self.pose.as_slice()
     }
 
+    // Marks every joint as dirty
     pub fn pose_ref_mut(&mut self) -> &mut [Pose] {
+        self.mark_all_dirty();
         &mut self.pose[..]
     }
 
@@ -98,6 +156,14 @@ impl Skeleton {
         self.world_pose.as_slice()
     }
 
+    pub fn inv_bind_pose_ref(&self) -> &[Option<Pose>] {
+        self.inv_bind_pose.as_slice()
+    }
+
+    pub fn inv_bind_pose_ref_mut(&mut self) -> &mut [Option<Pose>] {
+        &mut self.inv_bind_pose
+    }
+
     pub fn map_world_poses<'a, T, M: 'a + FnMut(Option<Pose>) -> T>(&'a self, mut map: M) -> impl Iterator<Item = T> + 'a {
         self.world_pose.iter().cloned().map(move |x| map(x))
     }
@@ -129,6 +195,10 @@ impl Skeleton {
         Ok(())
     } 
 
+    pub fn set_joint_inv_bind_pose(&mut self, id: usize, pose: Pose) {
+        self.inv_bind_pose[id] = Some(pose);
+    }
+
     pub fn build_joint_world_pose(&mut self, joint_id: usize) -> Option<Pose> {
         if joint_id >= self.tree.len() {
             return None;
@@ -152,12 +222,20 @@ impl Skeleton {
         Some(pose)
     }
 
+    // Takes a single linear pass when the joints are parent before child ordered, attached
+    // skeletons are updated as well
     pub fn build_world_poses(&mut self) {
+        self.build_world_poses_linear();
+        self.update_attachments();
+    }
+
+    pub fn build_world_poses_recursive(&mut self) {
         self.reset_world_poses();
 
         for i in 0..self.tree.len() {
             self.build_joint_world_pose(i);
         }
+        self.clear_dirty();
     }
 
     pub fn reset_world_poses(&mut self) {
@@ -220,3 +298,39 @@ impl Skeleton {
         }
     }
 }
+
+pub fn validate_tree(tree: &[Option<usize>]) -> Result<(), SkeletonError> {
+    const UNVISITED: u8 = 0;
+    const VISITING: u8 = 1;
+    const DONE: u8 = 2;
+
+    let mut state = vec![UNVISITED; tree.len()];
+    let mut path = vec![];
+
+    for joint in 0..tree.len() {
+        let mut current = joint;
+        path.clear();
+
+        // Walks up until a root or a joint that is already known to be fine
+        while state[current] == UNVISITED {
+            state[current] = VISITING;
+            path.push(current);
+
+            match tree[current] {
+                Some(parent) if parent >= tree.len() => return Err(SkeletonError::ParentOutOfRange { joint: current, parent }),
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+
+        if state[current] == VISITING && tree[current].is_some() {
+            return Err(SkeletonError::Cycle { joint: current });
+        }
+
+        for joint in &path {
+            state[*joint] = DONE;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use super::{Skeleton, validate_tree, error::SkeletonError};
+use std::collections::VecDeque;
+
+// Lists the joints so that every parent comes before its children, breadth first from the
+// roots with siblings kept in their current order
+pub fn topological_order(tree: &[Option<usize>]) -> Result<Vec<usize>, SkeletonError> {
+    validate_tree(tree)?;
+
+    let mut children = vec![vec![]; tree.len()];
+    let mut queue = VecDeque::new();
+    for (joint, parent) in tree.iter().enumerate() {
+        match parent {
+            Some(parent) => children[*parent].push(joint),
+            None => queue.push_back(joint),
+        }
+    }
+
+    let mut order = Vec::with_capacity(tree.len());
+    while let Some(joint) = queue.pop_front() {
+        order.push(joint);
+        queue.extend(children[joint].iter().cloned());
+    }
+
+    Ok(order)
+}
+
+// Turns a list of old indices in their new order into a table of old index -> new index
+pub fn order_to_remap(order: &[usize]) -> Vec<usize> {
+    let mut remap = vec![0; order.len()];
+    for (new, old) in order.iter().enumerate() {
+        remap[*old] = new;
+    }
+
+    remap
+}
+
+impl Skeleton {
+    // True if every parent has a lower index than its children
+    pub fn is_topologically_ordered(&self) -> bool {
+        self.tree.iter().enumerate().all(|(joint, parent)| parent.map(|x| x < joint).unwrap_or(true))
+    }
+
+    // Moves the joints so that `order[new index] = old index`, returns the remap from old to new
+    // indices that has to be applied to animations and meshes made for the old order. The order
+    // has to list every joint exactly once
+    pub fn reorder(&mut self, order: &[usize]) -> Result<Vec<usize>, SkeletonError> {
+        if order.len() != self.tree.len() {
+            return Err(SkeletonError::LengthMismatch { joints: self.tree.len(), values: order.len() });
+        }
+
+        let mut seen = vec![false; order.len()];
+        for old in order {
+            match seen.get_mut(*old) {
+                Some(seen) if !*seen => *seen = true,
+                _ => return Err(SkeletonError::InvalidOrder { joint: *old }),
+            }
+        }
+
+        let remap = order_to_remap(order);
+
+        self.tree = order.iter().map(|old| self.tree[*old].map(|parent| remap[parent])).collect();
+        self.pose = order.iter().map(|old| self.pose[*old]).collect();
+        self.world_pose = order.iter().map(|old| self.world_pose[*old]).collect();
+        self.inv_bind_pose = order.iter().map(|old| self.inv_bind_pose[*old]).collect();
+        self.names = order.iter().map(|old| self.names[*old].clone()).collect();
+        self.dirty = order.iter().map(|old| self.dirty[*old]).collect();
+        for socket in self.sockets.iter_mut() {
+            socket.joint = remap[socket.joint];
+        }
+        for attachment in self.attachments.iter_mut() {
+            attachment.joint = remap[attachment.joint];
+        }
+
+        Ok(remap)
+    }
+
+    // Puts the joints in parent before child order, see reorder for the returned remap
+    pub fn reorder_topological(&mut self) -> Result<Vec<usize>, SkeletonError> {
+        let order = topological_order(&self.tree)?;
+        self.reorder(&order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pose::Pose;
+
+    #[test]
+    fn validate_trees() {
+        let poses = |n| vec![Pose::pose_identity(); n];
+
+        assert!(Skeleton::from_tree_pose(vec![None, Some(0)], poses(2)).validate().is_ok());
+        assert!(validate_tree(&[Some(1), Some(0)]) == Err(SkeletonError::Cycle { joint: 0 }));
+        assert!(validate_tree(&[None, Some(4)]) == Err(SkeletonError::ParentOutOfRange { joint: 1, parent: 4 }));
+        assert!(Skeleton::from_tree_pose(vec![None, None], poses(2)).validate() == Err(SkeletonError::MultipleRoots { first: 0, second: 1 }));
+        assert!(validate_tree(&[Some(0)]) == Err(SkeletonError::Cycle { joint: 0 }));
+        assert!(!Skeleton::from_tree_pose(vec![None, None], poses(2)).contains_one_root());
+    }
+
+    #[test]
+    #[should_panic]
+    fn cyclic_tree_panics() {
+        Skeleton::from_tree_pose(vec![None, Some(2), Some(1)], vec![Pose::pose_identity(); 3]);
+    }
+
+    #[test]
+    fn reorder_parents_first() {
+        // 2 is the root, 0 is a child of 1
+        let tree = vec![Some(1), Some(2), None, Some(2)];
+        let poses = (0..4).map(|i| Pose::only_trans(i as f32, 0.0, 0.0)).collect();
+        let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+        skeleton.set_joint_name(0, "hand");
+        assert!(!skeleton.is_topologically_ordered());
+
+        let remap = skeleton.reorder_topological().unwrap();
+
+        assert!(remap == vec![3, 1, 0, 2]);
+        assert!(skeleton.is_topologically_ordered());
+        assert!(skeleton.tree_ref() == &[None, Some(0), Some(0), Some(1)]);
+        assert!(skeleton.find_joint("hand") == Some(3));
+        assert!(skeleton.pose_ref()[3].translation.x == 0.0);
+    }
+
+    #[test]
+    fn reject_invalid_orders() {
+        let mut skeleton = Skeleton::from_tree_pose(vec![None, Some(0), Some(0)], vec![Pose::pose_identity(); 3]);
+
+        assert!(skeleton.reorder(&[0, 1]) == Err(SkeletonError::LengthMismatch { joints: 3, values: 2 }));
+        assert!(skeleton.reorder(&[0, 1, 1]) == Err(SkeletonError::InvalidOrder { joint: 1 }));
+        assert!(skeleton.reorder(&[0, 1, 3]) == Err(SkeletonError::InvalidOrder { joint: 3 }));
+        assert!(skeleton.tree_ref() == &[None, Some(0), Some(0)]);
+
+        assert!(skeleton.reorder(&[0, 2, 1]) == Ok(vec![0, 2, 1]));
+    }
+}
@@ -0,0 +1,130 @@
+use super::{Skeleton, validate_tree, error::SkeletonError, attachment::Socket};
+use pose::Pose;
+use serde::{Serialize, Serializer, Deserialize, Deserializer, de};
+use serde_json;
+use std::io::{BufReader, BufWriter};
+use std::fs::File;
+use std::path::Path;
+use std::error::Error;
+
+// What gets stored for a skeleton, world poses are left out as they are rebuilt from the poses
+#[derive(Serialize)]
+struct SkeletonDataRef<'a> {
+    tree: &'a [Option<usize>],
+    pose: &'a [Pose],
+    inv_bind_pose: &'a [Option<Pose>],
+    names: &'a [Option<String>],
+    sockets: &'a [Socket],
+}
+
+#[derive(Deserialize)]
+struct SkeletonData {
+    tree: Vec<Option<usize>>,
+    pose: Vec<Pose>,
+    #[serde(default)]
+    inv_bind_pose: Vec<Option<Pose>>,
+    #[serde(default)]
+    names: Vec<Option<String>>,
+    #[serde(default)]
+    sockets: Vec<Socket>,
+}
+
+impl SkeletonData {
+    fn into_skeleton(self) -> Result<Skeleton, SkeletonError> {
+        let joints = self.tree.len();
+        if self.pose.len() != joints {
+            return Err(SkeletonError::LengthMismatch { joints, values: self.pose.len() });
+        }
+
+        // Inverse bind poses and names are optional
+        for values in &[self.inv_bind_pose.len(), self.names.len()] {
+            if *values != 0 && *values != joints {
+                return Err(SkeletonError::LengthMismatch { joints, values: *values });
+            }
+        }
+        validate_tree(&self.tree)?;
+        if let Some(socket) = self.sockets.iter().find(|x| x.joint >= joints) {
+            return Err(SkeletonError::SocketOutOfRange { joint: socket.joint });
+        }
+
+        let mut skeleton = Skeleton::from_tree_pose(self.tree, self.pose);
+        if self.inv_bind_pose.len() == joints {
+            skeleton.inv_bind_pose = self.inv_bind_pose;
+        }
+        if self.names.len() == joints {
+            skeleton.names = self.names;
+        }
+        skeleton.sockets = self.sockets;
+
+        Ok(skeleton)
+    }
+}
+
+impl Serialize for Skeleton {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SkeletonDataRef {
+            tree: &self.tree,
+            pose: &self.pose,
+            inv_bind_pose: &self.inv_bind_pose,
+            names: &self.names,
+            sockets: &self.sockets,
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Skeleton {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Skeleton, D::Error> {
+        let data = SkeletonData::deserialize(deserializer)?;
+        data.into_skeleton().map_err(de::Error::custom)
+    }
+}
+
+impl Skeleton {
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Skeleton, Box<Error>> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+
+        let skeleton = serde_json::from_reader(reader)?;
+        Ok(skeleton)
+    }
+
+    pub fn save_to(&self, path: impl AsRef<Path>) -> Result<(), Box<Error>> {
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+
+        serde_json::to_writer(writer, &self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let tree = vec![None, Some(0), Some(1)];
+        let poses = vec![Pose::only_trans(0.0, 1.0, 0.0); 3];
+        let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+        skeleton.set_joint_name(2, "head");
+        skeleton.build_inv_bind_poses();
+
+        let json = serde_json::to_string(&skeleton).unwrap();
+        let loaded: Skeleton = serde_json::from_str(&json).unwrap();
+
+        assert!(loaded.tree_ref() == skeleton.tree_ref());
+        assert!(loaded.find_joint("head") == Some(2));
+        assert!(loaded.inv_bind_pose_ref()[2].is_some());
+        assert!(loaded.world_pose_ref().iter().all(|x| x.is_none()));
+    }
+
+    #[test]
+    fn rejects_invalid_tree() {
+        let pose = serde_json::to_string(&Pose::pose_identity()).unwrap();
+        let cyclic = format!(r#"{{"tree": [1, 0], "pose": [{}, {}]}}"#, pose, pose);
+        assert!(serde_json::from_str::<Skeleton>(&cyclic).is_err());
+
+        let out_of_range = format!(r#"{{"tree": [null, 5], "pose": [{}, {}]}}"#, pose, pose);
+        assert!(serde_json::from_str::<Skeleton>(&out_of_range).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use super::{Skeleton, error::MissingInvBindpose};
+use pose::Pose;
+use glm::{self, Quat};
+
+// Computes the world poses of a parent before child ordered tree in a single pass over flat
+// arrays. Parents that come after their children give wrong results (and panic in debug builds)
+pub fn world_poses_linear(tree: &[Option<usize>], local: &[Pose], world: &mut [Pose]) {
+    assert!(tree.len() == local.len() && tree.len() == world.len());
+
+    for joint in 0..tree.len() {
+        world[joint] = match tree[joint] {
+            Some(parent) => {
+                debug_assert!(parent < joint);
+                world[parent] * local[joint]
+            }
+            None => local[joint],
+        };
+    }
+}
+
+impl Skeleton {
+    // Writes the world poses into the skeleton's own world poses without recursing when the
+    // skeleton is parent before child ordered, other skeletons take the recursive path
+    pub fn build_world_poses_linear(&mut self) {
+        if !self.is_topologically_ordered() {
+            self.build_world_poses_recursive();
+            return;
+        }
+
+        for joint in 0..self.tree.len() {
+            // Parents come first so their world pose is already built
+            let pose = match self.tree[joint].and_then(|parent| self.world_pose[parent]) {
+                Some(parent) => parent * self.pose[joint],
+                None => self.pose[joint],
+            };
+
+            self.world_pose[joint] = Some(pose);
+        }
+        self.clear_dirty();
+    }
+
+    // Overwrites the cached world pose of one joint without touching its local pose, use
+    // poses_from_world_poses afterwards to bring the local poses in line
+    pub(crate) fn set_joint_world_pose(&mut self, id: usize, pose: Pose) {
+        self.world_pose[id] = Some(pose);
+    }
+
+    // Turns a joint by a rotation given in world space, around the joint's own position. The
+    // world pose is computed from the local poses so earlier changes to the parents count
+    pub fn rotate_joint_world(&mut self, id: usize, rotation: &Quat) {
+        let world = match self.compute_joint_world_pose(id) {
+            Some(pose) => pose,
+            None => return,
+        };
+
+        // parent world * local' = rotation * world
+        let local = self.joint_pose_mut(id).unwrap();
+        let turned = local.rotation * glm::quat_conjugate(&world.rotation) * *rotation * world.rotation;
+        local.rotation = glm::quat_normalize(&turned);
+    }
+
+    // Same as build_world_poses_linear but into a flat array that the caller keeps around
+    pub fn world_poses_into(&self, world: &mut Vec<Pose>) {
+        world.clear();
+        if !self.is_topologically_ordered() {
+            world.extend((0..self.tree.len()).map(|x| self.compute_joint_world_pose(x).unwrap()));
+            return;
+        }
+
+        world.resize(self.tree.len(), Pose::pose_identity());
+        world_poses_linear(&self.tree, &self.pose, world);
+    }
+
+    // Computes the world poses into world and the skinning matrices straight into palette,
+    // every joint needs an inverse bind pose
+    pub fn matrix_palette_into(&self, world: &mut Vec<Pose>, palette: &mut Vec<[[f32; 4]; 4]>) -> Result<(), MissingInvBindpose> {
+        self.world_poses_into(world);
+
+        palette.clear();
+        for (world, inv) in world.iter().zip(&self.inv_bind_pose) {
+            let inv = inv.ok_or(MissingInvBindpose)?;
+            palette.push((world * inv).matrix().into());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glm::{self, Vec3};
+
+    #[test]
+    fn linear_matches_recursive() {
+        let tree = vec![None, Some(0), Some(1), Some(1), Some(3)];
+        let poses = (0..5).map(|i| {
+            let rotation = glm::quat_angle_axis(0.3 * i as f32, &Vec3::new(0.0, 0.0, 1.0));
+            Pose::without_scale(Vec3::new(1.0, i as f32, 0.0), rotation)
+        }).collect();
+        let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+        skeleton.build_inv_bind_poses();
+
+        skeleton.build_world_poses_recursive();
+        let recursive: Vec<Pose> = skeleton.world_pose_ref().iter().map(|x| x.unwrap()).collect();
+
+        let mut world = vec![];
+        let mut palette = vec![];
+        skeleton.matrix_palette_into(&mut world, &mut palette).unwrap();
+
+        for (a, b) in recursive.iter().zip(&world) {
+            assert!(glm::distance(&a.translation, &b.translation) < 1e-5);
+        }
+
+        // The rest pose skins to the identity
+        let identity: [[f32; 4]; 4] = glm::Mat4::identity().into();
+        for matrix in &palette {
+            for (a, b) in matrix.iter().flat_map(|x| x.iter()).zip(identity.iter().flat_map(|x| x.iter())) {
+                assert!((a - b).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn linear_handles_unordered() {
+        // 0 is a child of 1
+        let tree = vec![Some(1), None];
+        let poses = vec![Pose::only_trans(1.0, 0.0, 0.0), Pose::only_trans(0.0, 2.0, 0.0)];
+        let mut skeleton = Skeleton::from_tree_pose(tree, poses);
+
+        skeleton.build_world_poses_linear();
+        let child = skeleton.world_pose_ref()[0].unwrap();
+        assert!(glm::distance(&child.translation, &Vec3::new(1.0, 2.0, 0.0)) < 1e-5);
+
+        let mut world = vec![];
+        skeleton.world_poses_into(&mut world);
+        assert!(glm::distance(&world[0].translation, &Vec3::new(1.0, 2.0, 0.0)) < 1e-5);
+    }
+}